@@ -0,0 +1,192 @@
+//! Alerts sent while a command runs, as soon as its output matches a pattern.
+
+use regex::Regex;
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::{self, JoinHandle},
+    time::{sleep_until, Instant},
+};
+
+use crate::{
+    notifier::{Event, NotifierRegistry, Target},
+    output::{LineSink, Stream},
+};
+
+/// How long to wait for trailing context lines before sending an alert anyway.
+const CONTEXT_WAIT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub struct AlertOptions {
+    /// Lines matching any of these raise an alert...
+    pub alert_on: Vec<Regex>,
+    /// ...unless they also match one of these.
+    pub ignore: Vec<Regex>,
+    /// Lines of output included before and after the matching line.
+    pub context: usize,
+    /// Minimum time between two alerts. The last match in between is sent
+    /// once it is over, with the number of the others.
+    pub cooldown: Duration,
+}
+
+impl AlertOptions {
+    /// The alert pattern `line` matches, if it is not ignored.
+    fn matching(&self, line: &str) -> Option<&Regex> {
+        if self.ignore.iter().any(|ignore| ignore.is_match(line)) {
+            return None;
+        }
+        self.alert_on.iter().find(|pattern| pattern.is_match(line))
+    }
+}
+
+/// Forwards captured lines to the task started by [`start`].
+pub struct AlertSink {
+    lines: UnboundedSender<String>,
+}
+
+impl LineSink for AlertSink {
+    fn line(&mut self, _stream: Stream, line: &str) {
+        let _ = self.lines.send(line.to_string());
+    }
+}
+
+/// Starts scanning output for `options.alert_on`. The returned task ends once
+/// the sink is dropped and every alert has been delivered.
+pub fn start(
+    options: AlertOptions,
+    notifiers: Arc<NotifierRegistry>,
+    target: Target,
+    pid: Option<u32>,
+) -> (AlertSink, JoinHandle<()>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let scanner = Scanner {
+        options,
+        notifiers,
+        target,
+        pid,
+    };
+    (AlertSink { lines: tx }, task::spawn(scanner.run(rx)))
+}
+
+/// An alert waiting for its trailing context.
+struct Pending {
+    pattern: String,
+    line: String,
+    context: Vec<String>,
+    lines_left: usize,
+    deadline: Instant,
+    suppressed: usize,
+}
+
+struct Scanner {
+    options: AlertOptions,
+    notifiers: Arc<NotifierRegistry>,
+    target: Target,
+    pid: Option<u32>,
+}
+
+impl Scanner {
+    async fn run(self, mut lines: UnboundedReceiver<String>) {
+        let mut before = VecDeque::with_capacity(self.options.context);
+        let mut pending: Option<Pending> = None;
+        let mut last_alert: Option<Instant> = None;
+        let mut suppressed = 0;
+        // Pattern and line of the most recent match swallowed by the cool-down.
+        let mut latest: Option<(String, String)> = None;
+
+        loop {
+            let deadline = pending.as_ref().map(|alert| alert.deadline);
+            let summary_at = last_alert
+                .filter(|_| pending.is_none() && latest.is_some())
+                .map(|sent| sent + self.options.cooldown);
+            let line = tokio::select! {
+                line = lines.recv() => line,
+                _ = async { sleep_until(deadline.unwrap()).await }, if deadline.is_some() => {
+                    self.send(pending.take().unwrap()).await;
+                    continue;
+                }
+                _ = async { sleep_until(summary_at.unwrap()).await }, if summary_at.is_some() => {
+                    last_alert = Some(Instant::now());
+                    let summary = Self::summary(latest.take().unwrap(), &mut suppressed);
+                    self.send(summary).await;
+                    continue;
+                }
+            };
+            let Some(line) = line else {
+                break;
+            };
+
+            if let Some(alert) = &mut pending {
+                alert.context.push(line.clone());
+                alert.lines_left -= 1;
+                if alert.lines_left == 0 {
+                    self.send(pending.take().unwrap()).await;
+                }
+            } else if let Some(pattern) = self.options.matching(&line) {
+                let cooling_down =
+                    last_alert.is_some_and(|sent| sent.elapsed() < self.options.cooldown);
+                if cooling_down {
+                    suppressed += 1;
+                    latest = Some((pattern.as_str().to_string(), line.clone()));
+                } else {
+                    last_alert = Some(Instant::now());
+                    let mut context: Vec<String> = before.iter().cloned().collect();
+                    context.push(line.clone());
+                    let alert = Pending {
+                        pattern: pattern.as_str().to_string(),
+                        line: line.clone(),
+                        context,
+                        lines_left: self.options.context,
+                        deadline: Instant::now() + CONTEXT_WAIT,
+                        suppressed: std::mem::take(&mut suppressed),
+                    };
+                    latest = None;
+                    if alert.lines_left == 0 {
+                        self.send(alert).await;
+                    } else {
+                        pending = Some(alert);
+                    }
+                }
+            }
+
+            if self.options.context > 0 {
+                if before.len() == self.options.context {
+                    before.pop_front();
+                }
+                before.push_back(line);
+            }
+        }
+
+        if let Some(alert) = pending {
+            self.send(alert).await;
+        }
+        if let Some(latest) = latest {
+            self.send(Self::summary(latest, &mut suppressed)).await;
+        }
+    }
+
+    /// An alert for the last match swallowed by the cool-down, counting the
+    /// others as suppressed.
+    fn summary((pattern, line): (String, String), suppressed: &mut usize) -> Pending {
+        Pending {
+            pattern,
+            line,
+            context: Vec::new(),
+            lines_left: 0,
+            deadline: Instant::now(),
+            suppressed: std::mem::take(suppressed) - 1,
+        }
+    }
+
+    async fn send(&self, alert: Pending) {
+        let event = Event::alert(
+            self.target.clone(),
+            self.pid,
+            alert.pattern,
+            alert.line,
+            alert.suppressed,
+        )
+        .with_output(alert.context);
+        self.notifiers.notify(&event).await;
+    }
+}
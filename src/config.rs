@@ -0,0 +1,417 @@
+//! Notifier configuration, read from a TOML file and overridden by environment variables.
+//!
+//! ```toml
+//! [telegram]
+//! bot_token = "123456:ABC..."
+//! chat_id = "987654"
+//! api_url = "https://api.telegram.org"   # optional, e.g. a local Bot API server
+//! upload_output = "plain" # or "gzip", "off": output too long for a message is sent as a file
+//!
+//! [slack]
+//! webhook_url = "https://hooks.slack.com/services/..."
+//!
+//! [discord]
+//! webhook_url = "https://discord.com/api/webhooks/..."
+//!
+//! [email]
+//! host = "smtp.example.com"
+//! security = "starttls"   # or "tls", "none"
+//! username = "argus"      # optional, with password
+//! password = "..."
+//! from = "argus@example.com"
+//! to = ["owner@example.com"]
+//!
+//! [ntfy]
+//! url = "https://ntfy.sh/my-topic"
+//! token = "tk_..."        # optional
+//! priority = 3            # 1-5, used for successful runs
+//! failure_priority = 5
+//! tags = ["computer"]
+//! click = "https://ci.example.com"
+//!
+//! [gotify]
+//! url = "https://gotify.example.com"
+//! token = "app token"
+//! priority = 5
+//! failure_priority = 8
+//!
+//! [webhook]
+//! url = "https://tooling.example.com/argus"
+//! secret = "..."          # optional, signs requests with X-Argus-Signature
+//!
+//! [matrix]
+//! homeserver = "https://matrix.example.org"
+//! access_token = "syt_..."
+//! room_id = "!abcdefg:example.org"
+//!
+//! [desktop]               # also enabled by `--desktop`
+//! timeout_secs = 10       # optional, server default otherwise
+//! ```
+
+use serde::Deserialize;
+use std::{env, fmt, fs, io, path::PathBuf, time::Duration};
+
+use crate::notifier::{
+    DesktopNotifier, DiscordNotifier, EmailNotifier, GotifyNotifier, MatrixNotifier,
+    NotifierRegistry, NtfyNotifier, OutputUpload, SlackNotifier, SmtpSecurity, TelegramNotifier,
+    WebhookNotifier, TELEGRAM_API_URL,
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub telegram: Option<TelegramConfig>,
+    pub slack: Option<SlackConfig>,
+    pub discord: Option<DiscordConfig>,
+    pub email: Option<EmailConfig>,
+    pub ntfy: Option<NtfyConfig>,
+    pub gotify: Option<GotifyConfig>,
+    pub webhook: Option<WebhookConfig>,
+    pub matrix: Option<MatrixConfig>,
+    pub desktop: Option<DesktopConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramConfig {
+    pub bot_token: String,
+    pub chat_id: String,
+    pub api_url: Option<String>,
+    #[serde(default)]
+    pub upload_output: OutputUpload,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlackConfig {
+    pub webhook_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiscordConfig {
+    pub webhook_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NtfyConfig {
+    pub url: String,
+    pub token: Option<String>,
+    #[serde(default = "NtfyConfig::default_priority")]
+    pub priority: u8,
+    #[serde(default = "NtfyConfig::default_failure_priority")]
+    pub failure_priority: u8,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub click: Option<String>,
+}
+
+impl NtfyConfig {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            token: None,
+            priority: Self::default_priority(),
+            failure_priority: Self::default_failure_priority(),
+            tags: Vec::new(),
+            click: None,
+        }
+    }
+
+    fn default_priority() -> u8 {
+        3
+    }
+
+    fn default_failure_priority() -> u8 {
+        5
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GotifyConfig {
+    pub url: String,
+    pub token: String,
+    #[serde(default = "GotifyConfig::default_priority")]
+    pub priority: u8,
+    #[serde(default = "GotifyConfig::default_failure_priority")]
+    pub failure_priority: u8,
+}
+
+impl GotifyConfig {
+    fn default_priority() -> u8 {
+        5
+    }
+
+    fn default_failure_priority() -> u8 {
+        8
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MatrixConfig {
+    pub homeserver: String,
+    pub access_token: String,
+    pub room_id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DesktopConfig {
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    /// A notifier section is present but cannot be used.
+    Notifier(&'static str, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "invalid config {}: {}", path.display(), e),
+            ConfigError::Notifier(name, e) => write!(f, "invalid {} notifier: {}", name, e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Loads the config file and applies environment overrides.
+    ///
+    /// An explicitly given `path` must exist; the default location
+    /// (`$XDG_CONFIG_HOME/argus/config.toml`) is optional.
+    pub fn load(path: Option<PathBuf>) -> Result<Self, ConfigError> {
+        let mut config = match path {
+            Some(path) => Self::read(path)?,
+            None => match default_path() {
+                Some(path) if path.exists() => Self::read(path)?,
+                _ => Self::default(),
+            },
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    fn read(path: PathBuf) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(&path).map_err(|e| ConfigError::Read(path.clone(), e))?;
+        toml::from_str(&text).map_err(|e| ConfigError::Parse(path, e))
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        match (
+            &mut self.telegram,
+            env::var("BOT_TOKEN").ok(),
+            env::var("CHAT_ID").ok(),
+        ) {
+            (Some(telegram), bot_token, chat_id) => {
+                if let Some(bot_token) = bot_token {
+                    telegram.bot_token = bot_token;
+                }
+                if let Some(chat_id) = chat_id {
+                    telegram.chat_id = chat_id;
+                }
+            }
+            (None, Some(bot_token), Some(chat_id)) => {
+                self.telegram = Some(TelegramConfig {
+                    bot_token,
+                    chat_id,
+                    api_url: None,
+                    upload_output: OutputUpload::default(),
+                });
+            }
+            (None, Some(_), None) => {
+                return Err(ConfigError::Notifier(
+                    "Telegram",
+                    "BOT_TOKEN is set but CHAT_ID is not".to_string(),
+                ))
+            }
+            (None, None, Some(_)) => {
+                return Err(ConfigError::Notifier(
+                    "Telegram",
+                    "CHAT_ID is set but BOT_TOKEN is not".to_string(),
+                ))
+            }
+            (None, None, None) => {}
+        }
+        if let Ok(webhook_url) = env::var("SLACK_WEBHOOK_URL") {
+            self.slack = Some(SlackConfig { webhook_url });
+        }
+        if let Ok(webhook_url) = env::var("DISCORD_WEBHOOK_URL") {
+            self.discord = Some(DiscordConfig { webhook_url });
+        }
+        if let Ok(url) = env::var("NTFY_URL") {
+            match &mut self.ntfy {
+                Some(ntfy) => ntfy.url = url,
+                None => self.ntfy = Some(NtfyConfig::new(url)),
+            }
+        }
+        match (
+            &mut self.gotify,
+            env::var("GOTIFY_URL").ok(),
+            env::var("GOTIFY_TOKEN").ok(),
+        ) {
+            (Some(gotify), url, token) => {
+                if let Some(url) = url {
+                    gotify.url = url;
+                }
+                if let Some(token) = token {
+                    gotify.token = token;
+                }
+            }
+            (None, Some(url), Some(token)) => {
+                self.gotify = Some(GotifyConfig {
+                    url,
+                    token,
+                    priority: GotifyConfig::default_priority(),
+                    failure_priority: GotifyConfig::default_failure_priority(),
+                });
+            }
+            (None, Some(_), None) => {
+                return Err(ConfigError::Notifier(
+                    "Gotify",
+                    "GOTIFY_URL is set but GOTIFY_TOKEN is not".to_string(),
+                ))
+            }
+            (None, None, Some(_)) => {
+                return Err(ConfigError::Notifier(
+                    "Gotify",
+                    "GOTIFY_TOKEN is set but GOTIFY_URL is not".to_string(),
+                ))
+            }
+            (None, None, None) => {}
+        }
+        if let Ok(url) = env::var("ARGUS_WEBHOOK_URL") {
+            let secret = env::var("ARGUS_WEBHOOK_SECRET").ok();
+            self.webhook = Some(WebhookConfig { url, secret });
+        }
+        if let (Ok(homeserver), Ok(access_token), Ok(room_id)) = (
+            env::var("MATRIX_HOMESERVER"),
+            env::var("MATRIX_ACCESS_TOKEN"),
+            env::var("MATRIX_ROOM_ID"),
+        ) {
+            self.matrix = Some(MatrixConfig {
+                homeserver,
+                access_token,
+                room_id,
+            });
+        }
+        Ok(())
+    }
+
+    /// Builds a registry containing every configured notifier.
+    pub fn notifiers(&self) -> Result<NotifierRegistry, ConfigError> {
+        let mut registry = NotifierRegistry::new();
+        if let Some(telegram) = &self.telegram {
+            registry.register(TelegramNotifier::new(
+                telegram.api_url.as_deref().unwrap_or(TELEGRAM_API_URL),
+                &telegram.bot_token,
+                &telegram.chat_id,
+                telegram.upload_output,
+            ));
+        }
+        if let Some(slack) = &self.slack {
+            registry.register(SlackNotifier::new(&slack.webhook_url));
+        }
+        if let Some(discord) = &self.discord {
+            registry.register(DiscordNotifier::new(&discord.webhook_url));
+        }
+        if let Some(email) = &self.email {
+            let credentials = match (&email.username, &email.password) {
+                (Some(username), Some(password)) => Some((username.clone(), password.clone())),
+                (None, None) => None,
+                _ => {
+                    return Err(ConfigError::Notifier(
+                        "email",
+                        "username and password must be set together".to_string(),
+                    ))
+                }
+            };
+            let notifier = EmailNotifier::new(
+                &email.host,
+                email.port,
+                email.security,
+                credentials,
+                &email.from,
+                &email.to,
+            )
+            .map_err(|e| ConfigError::Notifier("email", e.to_string()))?;
+            registry.register(notifier);
+        }
+        if let Some(ntfy) = &self.ntfy {
+            for priority in [ntfy.priority, ntfy.failure_priority] {
+                if !(1..=5).contains(&priority) {
+                    return Err(ConfigError::Notifier(
+                        "ntfy",
+                        format!("priority {} is outside 1-5", priority),
+                    ));
+                }
+            }
+            registry.register(NtfyNotifier::new(
+                &ntfy.url,
+                ntfy.token.clone(),
+                ntfy.priority,
+                ntfy.failure_priority,
+                ntfy.tags.clone(),
+                ntfy.click.clone(),
+            ));
+        }
+        if let Some(gotify) = &self.gotify {
+            registry.register(GotifyNotifier::new(
+                &gotify.url,
+                &gotify.token,
+                gotify.priority,
+                gotify.failure_priority,
+            ));
+        }
+        if let Some(webhook) = &self.webhook {
+            registry.register(WebhookNotifier::new(&webhook.url, webhook.secret.clone()));
+        }
+        if let Some(matrix) = &self.matrix {
+            let notifier =
+                MatrixNotifier::new(&matrix.homeserver, &matrix.access_token, &matrix.room_id)
+                    .map_err(|e| ConfigError::Notifier("Matrix", e.to_string()))?;
+            registry.register(notifier);
+        }
+        if let Some(desktop) = &self.desktop {
+            registry.register(DesktopNotifier::new(
+                desktop.timeout_secs.map(Duration::from_secs),
+            ));
+        }
+        Ok(registry)
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("argus").join("config.toml"))
+}
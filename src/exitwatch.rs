@@ -0,0 +1,87 @@
+//! Waiting for processes argus did not spawn to exit.
+//!
+//! A pidfd becomes readable exactly when its process exits, which needs
+//! Linux 5.3. Older kernels, and sandboxes that forbid the syscall, fall back
+//! to polling `/proc`. Either way the process is identified by its PID and
+//! start time, so a new process that reuses the PID is not mistaken for it.
+
+use std::{
+    io,
+    os::fd::{FromRawFd, OwnedFd},
+    time::Duration,
+};
+use tokio::{io::unix::AsyncFd, time::sleep};
+
+use crate::procfs;
+
+/// How often `/proc` is checked when no pidfd is available.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+fn pidfd_open(pid: u32) -> io::Result<OwnedFd> {
+    // SAFETY: pidfd_open takes no pointers. On success it returns a new file
+    // descriptor nobody else owns.
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as i32) })
+}
+
+/// A running process to wait for.
+pub struct ExitWatch {
+    pid: u32,
+    /// When the process started, relative to boot; tells it apart from later
+    /// processes with the same PID.
+    start_time: Duration,
+    pidfd: Option<AsyncFd<OwnedFd>>,
+}
+
+impl ExitWatch {
+    /// Starts watching `pid`, or returns `None` if it is not running.
+    pub fn new(pid: u32) -> Option<Self> {
+        let stat = procfs::stat(pid).filter(|stat| stat.is_running())?;
+        let pidfd = match pidfd_open(pid) {
+            Ok(fd) => match AsyncFd::new(fd) {
+                Ok(fd) => Some(fd),
+                Err(e) => {
+                    eprintln!("Cannot wait on pidfd, polling /proc instead: {}", e);
+                    None
+                }
+            },
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => return None,
+            // ENOSYS on kernels before 5.3, EPERM under some seccomp filters.
+            Err(_) => None,
+        };
+        let watch = Self {
+            pid,
+            start_time: stat.start_time,
+            pidfd,
+        };
+        // The process may have exited and its PID been reused before the
+        // pidfd was opened, in which case the pidfd refers to the newcomer.
+        watch.is_running().then_some(watch)
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Whether the watched process is still running.
+    pub fn is_running(&self) -> bool {
+        procfs::stat(self.pid)
+            .is_some_and(|stat| stat.start_time == self.start_time && stat.is_running())
+    }
+
+    /// Completes once the process has exited.
+    pub async fn exited(&self) {
+        if let Some(pidfd) = &self.pidfd {
+            // The pidfd only ever becomes readable once, when the process exits.
+            if pidfd.readable().await.is_ok() {
+                return;
+            }
+        }
+        while self.is_running() {
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Periodic "still running" notifications for long jobs.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::time::{self, Instant as TokioInstant};
+
+use crate::{
+    notifier::{Event, NotifierRegistry, ResourceUsage, Target},
+    procfs,
+};
+
+/// Measures CPU use between consecutive samples.
+#[derive(Default)]
+struct UsageSampler {
+    /// When the previous sample was taken, of which processes, and their CPU time.
+    last: Option<(Instant, Vec<u32>, Duration)>,
+}
+
+impl UsageSampler {
+    /// Combined usage of `pids`. CPU use is averaged since the previous
+    /// sample, or over the processes' lifetime when there is none.
+    fn sample(&mut self, pids: &[u32]) -> ResourceUsage {
+        let now = Instant::now();
+        let stats: Vec<_> = pids.iter().filter_map(|&pid| procfs::stat(pid)).collect();
+        if stats.is_empty() {
+            self.last = None;
+            return ResourceUsage::default();
+        }
+        let cpu_time: Duration = stats.iter().map(|stat| stat.cpu_time).sum();
+        let cpu_percent = match &self.last {
+            Some((at, last_pids, last_cpu_time)) if last_pids == pids => {
+                let wall = now.duration_since(*at).as_secs_f64();
+                let cpu = cpu_time.saturating_sub(*last_cpu_time).as_secs_f64();
+                (wall > 0.0).then(|| cpu / wall * 100.0)
+            }
+            _ => procfs::uptime().map(|uptime| {
+                stats
+                    .iter()
+                    .map(|stat| {
+                        let age = uptime.saturating_sub(stat.start_time).as_secs_f64();
+                        if age > 0.0 {
+                            stat.cpu_time.as_secs_f64() / age * 100.0
+                        } else {
+                            0.0
+                        }
+                    })
+                    .sum()
+            }),
+        };
+        self.last = Some((now, pids.to_vec(), cpu_time));
+        let memory: Vec<u64> = pids.iter().filter_map(|&pid| procfs::memory(pid)).collect();
+        ResourceUsage {
+            cpu_percent,
+            memory: (!memory.is_empty()).then(|| memory.iter().sum()),
+        }
+    }
+}
+
+/// Sends a heartbeat event every `interval` while a target runs.
+pub struct Heartbeat {
+    interval: Duration,
+    next: TokioInstant,
+    notifiers: Arc<NotifierRegistry>,
+    target: Target,
+    started_at: Instant,
+    usage: UsageSampler,
+}
+
+impl Heartbeat {
+    pub fn new(
+        interval: Duration,
+        notifiers: Arc<NotifierRegistry>,
+        target: Target,
+        started_at: Instant,
+    ) -> Self {
+        Self {
+            interval,
+            next: TokioInstant::now() + interval,
+            notifiers,
+            target,
+            started_at,
+            usage: UsageSampler::default(),
+        }
+    }
+
+    pub fn is_due(&self) -> bool {
+        TokioInstant::now() >= self.next
+    }
+
+    /// Waits until the next heartbeat is due.
+    pub async fn wait(&self) {
+        time::sleep_until(self.next).await;
+    }
+
+    /// Reports that `pids` are still running. `pid` is the one the event is about, if any.
+    pub async fn send(&mut self, pid: Option<u32>, pids: &[u32], last_line: Option<String>) {
+        self.next = TokioInstant::now() + self.interval;
+        let event = Event::heartbeat(
+            self.target.clone(),
+            pid,
+            self.started_at.elapsed(),
+            self.usage.sample(pids),
+            last_line,
+        );
+        self.notifiers.notify(&event).await;
+    }
+}
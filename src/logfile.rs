@@ -0,0 +1,318 @@
+//! Persisting captured output to size-rotated log files.
+
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::output::{LineSink, Stream};
+
+/// Where and how output is logged.
+#[derive(Debug, Clone)]
+pub struct LogOptions {
+    pub dir: PathBuf,
+    /// Write stdout and stderr to separate files instead of one interleaved file.
+    pub split: bool,
+    /// Rotate a file once it grows past this many bytes.
+    pub max_size: u64,
+    /// Number of rotated files kept next to the active one.
+    pub keep: usize,
+    /// Gzip rotated files.
+    pub compress: bool,
+}
+
+/// Parses a byte size such as `512`, `64K`, `10M` or `1G` (binary multiples).
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let (digits, multiplier) = match text.char_indices().last() {
+        Some((i, 'k' | 'K')) => (&text[..i], 1 << 10),
+        Some((i, 'm' | 'M')) => (&text[..i], 1 << 20),
+        Some((i, 'g' | 'G')) => (&text[..i], 1 << 30),
+        _ => (text, 1),
+    };
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid size '{}', expected e.g. 512K or 10M", text))?;
+    value
+        .checked_mul(multiplier)
+        .filter(|&size| size > 0)
+        .ok_or_else(|| format!("size '{}' is out of range", text))
+}
+
+/// A log file that is renamed to `<path>.1`, `<path>.2`, ... once it is full.
+struct RotatingFile {
+    path: PathBuf,
+    file: BufWriter<File>,
+    written: u64,
+}
+
+impl RotatingFile {
+    fn create(path: PathBuf) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(&path)?);
+        Ok(Self {
+            path,
+            file,
+            written: 0,
+        })
+    }
+
+    fn write_line(&mut self, line: &str, options: &LogOptions) -> io::Result<()> {
+        if self.written > 0 && self.written + line.len() as u64 + 1 > options.max_size {
+            self.rotate(options)?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.written += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize, options: &LogOptions) -> PathBuf {
+        let suffix = if options.compress { ".gz" } else { "" };
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}{}", index, suffix));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self, options: &LogOptions) -> io::Result<()> {
+        self.file.flush()?;
+        if options.keep == 0 {
+            // Nothing to keep: start over in the same file.
+            self.file = BufWriter::new(File::create(&self.path)?);
+            self.written = 0;
+            return Ok(());
+        }
+
+        let _ = fs::remove_file(self.rotated_path(options.keep, options));
+        for index in (1..options.keep).rev() {
+            let from = self.rotated_path(index, options);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1, options))?;
+            }
+        }
+        let first = self.rotated_path(1, options);
+        if options.compress {
+            gzip(&self.path, &first)?;
+            fs::remove_file(&self.path)?;
+        } else {
+            fs::rename(&self.path, &first)?;
+        }
+
+        self.file = BufWriter::new(File::create(&self.path)?);
+        self.written = 0;
+        Ok(())
+    }
+}
+
+fn gzip(from: &Path, to: &Path) -> io::Result<()> {
+    let mut encoder = GzEncoder::new(BufWriter::new(File::create(to)?), Compression::default());
+    io::copy(&mut File::open(from)?, &mut encoder)?;
+    encoder.finish()?.flush()
+}
+
+/// Writes every captured line, prefixed with a timestamp, to the log files
+/// of one run.
+pub struct OutputLog {
+    options: LogOptions,
+    /// One combined file, or stdout and stderr files when split.
+    files: Vec<RotatingFile>,
+    failed: bool,
+}
+
+impl OutputLog {
+    /// Creates the log files for a run in `options.dir`, named after the start
+    /// time and `pid`.
+    pub fn create(options: LogOptions, pid: Option<u32>) -> io::Result<Self> {
+        fs::create_dir_all(&options.dir)?;
+        let started = humantime::format_rfc3339_seconds(SystemTime::now())
+            .to_string()
+            .replace(':', "-");
+        let stem = match pid {
+            Some(pid) => format!("{}-{}", started, pid),
+            None => started,
+        };
+        let names = if options.split {
+            vec![
+                format!("{}.stdout.log", stem),
+                format!("{}.stderr.log", stem),
+            ]
+        } else {
+            vec![format!("{}.log", stem)]
+        };
+        let files = names
+            .into_iter()
+            .map(|name| RotatingFile::create(options.dir.join(name)))
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            options,
+            files,
+            failed: false,
+        })
+    }
+
+    /// Paths of the active log files.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|file| file.path.clone()).collect()
+    }
+
+    fn fail(&mut self, e: io::Error) {
+        eprintln!("Failed to write output log, logging stopped: {}", e);
+        self.failed = true;
+    }
+}
+
+impl LineSink for OutputLog {
+    fn line(&mut self, stream: Stream, line: &str) {
+        if self.failed {
+            return;
+        }
+        let timestamp = humantime::format_rfc3339_millis(SystemTime::now());
+        let (file, entry) = if self.options.split {
+            let index = match stream {
+                Stream::Stdout => 0,
+                Stream::Stderr => 1,
+            };
+            (&mut self.files[index], format!("{} {}", timestamp, line))
+        } else {
+            let entry = format!("{} [{}] {}", timestamp, stream.name(), line);
+            (&mut self.files[0], entry)
+        };
+        if let Err(e) = file.write_line(&entry, &self.options) {
+            self.fail(e);
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.failed {
+            return;
+        }
+        if let Err(e) = self.files.iter_mut().try_for_each(|file| file.file.flush()) {
+            self.fail(e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    /// A fresh directory for one test, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("argus-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn options(dir: &TempDir, keep: usize, compress: bool) -> LogOptions {
+        LogOptions {
+            dir: dir.0.clone(),
+            split: false,
+            max_size: 10,
+            keep,
+            compress,
+        }
+    }
+
+    fn read(path: PathBuf) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size(" 64k "), Ok(64 << 10));
+        assert_eq!(parse_size("10M"), Ok(10 << 20));
+        assert_eq!(parse_size("1G"), Ok(1 << 30));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("10T").is_err());
+        assert!(parse_size("-1K").is_err());
+        assert!(parse_size("99999999999999999999G").is_err());
+        assert!(parse_size("18446744073709551615K").is_err());
+    }
+
+    #[test]
+    fn rotates_and_keeps_the_newest_files() {
+        let dir = TempDir::new("rotate");
+        let options = options(&dir, 2, false);
+        let path = dir.0.join("out.log");
+        let mut file = RotatingFile::create(path.clone()).unwrap();
+        for line in ["one", "two", "three", "four", "five"] {
+            file.write_line(line, &options).unwrap();
+        }
+        file.file.flush().unwrap();
+
+        assert_eq!(read(path.clone()), "four\nfive\n");
+        assert_eq!(read(file.rotated_path(1, &options)), "three\n");
+        assert_eq!(read(file.rotated_path(2, &options)), "one\ntwo\n");
+        assert!(!file.rotated_path(3, &options).exists());
+    }
+
+    #[test]
+    fn overlong_lines_are_written_whole() {
+        let dir = TempDir::new("overlong");
+        let options = options(&dir, 1, false);
+        let path = dir.0.join("out.log");
+        let mut file = RotatingFile::create(path.clone()).unwrap();
+        file.write_line("a line longer than the limit", &options)
+            .unwrap();
+        file.write_line("next", &options).unwrap();
+        file.file.flush().unwrap();
+
+        assert_eq!(read(path), "next\n");
+        assert_eq!(
+            read(file.rotated_path(1, &options)),
+            "a line longer than the limit\n"
+        );
+    }
+
+    #[test]
+    fn without_kept_files_starts_over() {
+        let dir = TempDir::new("keep-none");
+        let options = options(&dir, 0, false);
+        let path = dir.0.join("out.log");
+        let mut file = RotatingFile::create(path.clone()).unwrap();
+        for line in ["one", "two", "three"] {
+            file.write_line(line, &options).unwrap();
+        }
+        file.file.flush().unwrap();
+
+        assert_eq!(read(path), "three\n");
+        assert_eq!(fs::read_dir(&dir.0).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn compresses_rotated_files() {
+        let dir = TempDir::new("compress");
+        let options = options(&dir, 1, true);
+        let path = dir.0.join("out.log");
+        let mut file = RotatingFile::create(path.clone()).unwrap();
+        for line in ["one", "two", "three"] {
+            file.write_line(line, &options).unwrap();
+        }
+
+        let rotated = file.rotated_path(1, &options);
+        assert!(rotated.to_string_lossy().ends_with("out.log.1.gz"));
+        let mut text = String::new();
+        GzDecoder::new(File::open(rotated).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "one\ntwo\n");
+    }
+}
@@ -1,8 +1,47 @@
-use clap::{Parser, Subcommand};
-use reqwest::Client;
+mod alert;
+mod config;
+mod exitwatch;
+mod heartbeat;
+mod logfile;
+mod namewatch;
+mod notifier;
+mod output;
+mod procevents;
+mod procfs;
+mod progress;
+mod restart;
+mod stall;
+
+use alert::AlertOptions;
+use clap::{Args, Parser, Subcommand};
+use config::Config;
+use exitwatch::ExitWatch;
+use heartbeat::Heartbeat;
+use logfile::{parse_size, LogOptions, OutputLog};
+use namewatch::{parse_user, Change, Matcher, NameWatcher};
+use notifier::{
+    describe_status, describe_timeout_status, exit_code, format_duration, Activity, Event,
+    NotifierRegistry, Target,
+};
+use output::{CapturedOutput, LineSink, OutputCapture, OutputSpool};
+use progress::ProgressOptions;
+use regex::Regex;
+use restart::{Decision, RestartOptions, RestartPolicy, Restarter};
 use spinners::{Spinner, Spinners};
-use std::{env, process::Command as StdCommand, time::Duration};
-use tokio::{process::Child, process::Command as TokioCommand, task, time::sleep};
+use stall::StallDetector;
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{self, ExitStatus},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    process::Child,
+    process::Command as TokioCommand,
+    task,
+    time::{self, sleep, sleep_until},
+};
 
 #[derive(Parser)]
 #[command(
@@ -10,179 +49,782 @@ use tokio::{process::Child, process::Command as TokioCommand, task, time::sleep}
     about = "Monitor processes by PID, name, or execute commands."
 )]
 struct Cli {
+    /// Notifier config file [default: $XDG_CONFIG_HOME/argus/config.toml]
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Also show desktop notifications on this machine
+    #[arg(long, global = true)]
+    desktop: bool,
+    /// Send a status update with elapsed time and CPU/memory use this often,
+    /// e.g. 30m or 6h
+    #[arg(long, global = true, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    heartbeat: Option<Duration>,
+    /// Notify when a command prints nothing, or a process uses no CPU, for this
+    /// long, and again once it is active again
+    #[arg(long, global = true, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    stall_timeout: Option<Duration>,
     #[command(subcommand)]
     command: Commands,
 }
 
+// Parsed once at startup, the size of the variants does not matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Monitor a process by PID
     Pid { pid: u32 },
-    /// Monitor a process by name
-    Name { process_name: String },
+    /// Monitor processes whose name matches a regex, like pgrep
+    Name {
+        #[arg(value_parser = Regex::new)]
+        process_name: Regex,
+        /// Only processes whose command line, arguments joined by spaces,
+        /// matches this regex
+        #[arg(long, value_parser = Regex::new)]
+        match_cmdline: Option<Regex>,
+        /// Only processes running as this user, by name or UID
+        #[arg(long, value_parser = parse_user)]
+        user: Option<u32>,
+        /// Only direct children of this PID
+        #[arg(long)]
+        parent: Option<u32>,
+        /// Match the whole process name instead of any part of it
+        #[arg(long)]
+        exact: bool,
+        /// Keep waiting for new matching processes once all have exited,
+        /// until interrupted
+        #[arg(long)]
+        keep_watching: bool,
+    },
     /// Execute a command and monitor it
-    Exec { command: String },
+    Exec {
+        /// Program and arguments, e.g. `-- make -j8`; a single string with --shell
+        #[arg(required = true, trailing_var_arg = true, value_name = "COMMAND")]
+        command: Vec<String>,
+        /// Run the command through a shell, `sh` unless one is given with --shell=SHELL
+        #[arg(long, value_name = "SHELL", num_args = 0..=1, require_equals = true, default_missing_value = "sh")]
+        shell: Option<String>,
+        /// Number of trailing output lines attached to the finish notification
+        #[arg(long, default_value_t = 20)]
+        tail_lines: usize,
+        #[command(flatten)]
+        log: LogArgs,
+        #[command(flatten)]
+        alert: AlertArgs,
+        #[command(flatten)]
+        progress: ProgressArgs,
+        #[command(flatten)]
+        timeout: TimeoutArgs,
+        #[command(flatten)]
+        restart: RestartArgs,
+    },
+}
+
+#[derive(Args)]
+struct AlertArgs {
+    /// Send an alert as soon as an output line matches this regex (repeatable)
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    alert_on: Vec<Regex>,
+    /// Never alert on lines matching this regex (repeatable)
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new, requires = "alert_on")]
+    ignore: Vec<Regex>,
+    /// Lines of output to include before and after a matching line
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    alert_context: usize,
+    /// Minimum time between alerts, e.g. 30s or 5m; matches in between are summed up afterwards
+    #[arg(long, value_name = "DURATION", default_value = "1m", value_parser = humantime::parse_duration)]
+    alert_cooldown: Duration,
+}
+
+impl AlertArgs {
+    fn options(&self) -> Option<AlertOptions> {
+        if self.alert_on.is_empty() {
+            return None;
+        }
+        Some(AlertOptions {
+            alert_on: self.alert_on.clone(),
+            ignore: self.ignore.clone(),
+            context: self.alert_context,
+            cooldown: self.alert_cooldown,
+        })
+    }
+}
+
+#[derive(Args)]
+struct RestartArgs {
+    /// Start the command again when it exits
+    #[arg(long, value_name = "POLICY")]
+    restart: Option<RestartPolicy>,
+    /// Give up after this many restarts [default: unlimited]
+    #[arg(long, value_name = "N", requires = "restart")]
+    max_retries: Option<usize>,
+    /// Delay before the first restart, doubled after each quick failure
+    #[arg(long, value_name = "DURATION", default_value = "1s", value_parser = humantime::parse_duration, requires = "restart")]
+    restart_delay: Duration,
+    /// Upper bound for the delay between restarts
+    #[arg(long, value_name = "DURATION", default_value = "5m", value_parser = humantime::parse_duration, requires = "restart")]
+    restart_max_delay: Duration,
+    /// Give up when the command fails this many times within --crash-loop-window (0 disables)
+    #[arg(long, value_name = "N", default_value_t = 5, requires = "restart")]
+    crash_loop: usize,
+    /// Time window for --crash-loop; runs lasting longer also reset the backoff
+    #[arg(long, value_name = "DURATION", default_value = "1m", value_parser = humantime::parse_duration, requires = "restart")]
+    crash_loop_window: Duration,
+}
+
+impl RestartArgs {
+    fn options(&self) -> Option<RestartOptions> {
+        Some(RestartOptions {
+            policy: self.restart?,
+            max_restarts: self.max_retries,
+            delay: self.restart_delay,
+            max_delay: self.restart_max_delay,
+            crash_loop_failures: self.crash_loop,
+            crash_loop_window: self.crash_loop_window,
+        })
+    }
+}
+
+#[derive(Args)]
+struct TimeoutArgs {
+    /// Stop the command once it has run this long, e.g. 90m; it then exits with 124.
+    /// The command's stdin is /dev/null instead of the terminal
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    timeout: Option<Duration>,
+    /// After --timeout, how long to wait between SIGTERM and SIGKILL
+    #[arg(long, value_name = "DURATION", default_value = "10s", value_parser = humantime::parse_duration, requires = "timeout")]
+    kill_after: Duration,
+}
+
+impl TimeoutArgs {
+    fn limit(&self) -> Option<TimeLimit> {
+        Some(TimeLimit {
+            limit: self.timeout?,
+            grace: self.kill_after,
+        })
+    }
+}
+
+/// How long a command may run, and how long it gets to exit once asked to.
+#[derive(Clone, Copy)]
+struct TimeLimit {
+    limit: Duration,
+    grace: Duration,
+}
+
+/// Exit code for commands stopped by --timeout, the same as timeout(1) uses.
+const TIMEOUT_EXIT_CODE: i32 = 124;
+
+#[derive(Args)]
+struct ProgressArgs {
+    /// Detect progress (tqdm bars, "epoch 3/50", "42%") in the output and keep
+    /// a status message updated with it
+    #[arg(long)]
+    progress: bool,
+    /// Detect progress with this regex instead, using named groups percent,
+    /// current, total, rate and eta; implies --progress
+    #[arg(long, value_name = "REGEX", value_parser = progress::parse_pattern)]
+    progress_regex: Option<Regex>,
+    /// Minimum time between progress updates, e.g. 10s or 1m
+    #[arg(long, value_name = "DURATION", default_value = "10s", value_parser = humantime::parse_duration)]
+    progress_interval: Duration,
+}
+
+impl ProgressArgs {
+    fn options(&self) -> Option<ProgressOptions> {
+        if !self.progress && self.progress_regex.is_none() {
+            return None;
+        }
+        Some(ProgressOptions {
+            pattern: self.progress_regex.clone(),
+            interval: self.progress_interval,
+        })
+    }
+}
+
+#[derive(Args)]
+struct LogArgs {
+    /// Write the command's output to timestamped log files in this directory
+    #[arg(long, value_name = "DIR")]
+    log_dir: Option<PathBuf>,
+    /// Log stdout and stderr to separate files instead of one interleaved file
+    #[arg(long, requires = "log_dir")]
+    log_split: bool,
+    /// Rotate log files once they reach this size, e.g. 512K, 10M, 1G
+    #[arg(long, value_name = "SIZE", default_value = "10M", value_parser = parse_size, requires = "log_dir")]
+    log_max_size: u64,
+    /// Number of rotated log files to keep
+    #[arg(long, value_name = "N", default_value_t = 5, requires = "log_dir")]
+    log_keep: usize,
+    /// Gzip rotated log files
+    #[arg(long, requires = "log_dir")]
+    log_compress: bool,
 }
 
-async fn send_telegram_message(bot_token: &str, chat_id: &str, message: &str) {
-    let url = format!("https://api.telegram.org/bot{}/sendMessage", bot_token);
-    let client = Client::new();
-    if let Err(e) = client
-        .post(&url)
-        .form(&[("chat_id", chat_id), ("text", message)])
-        .send()
-        .await
-    {
-        eprintln!("Failed to send Telegram message: {}", e);
+impl LogArgs {
+    fn options(&self) -> Option<LogOptions> {
+        Some(LogOptions {
+            dir: self.log_dir.clone()?,
+            split: self.log_split,
+            max_size: self.log_max_size,
+            keep: self.log_keep,
+            compress: self.log_compress,
+        })
     }
 }
 
-async fn monitor_process(mut child: Child) {
-    match child.wait().await {
+/// How a monitored command ended.
+struct Completion {
+    status: Option<ExitStatus>,
+    /// The last `tail_lines` lines of combined stdout/stderr.
+    output: CapturedOutput,
+    /// Whether the command was stopped for exceeding its time limit.
+    timed_out: bool,
+}
+
+/// Sends `signal` to the process group led by `pid`.
+fn signal_group(pid: Option<u32>, signal: i32) {
+    if let Some(pid) = pid {
+        // SAFETY: killpg has no memory safety preconditions.
+        unsafe { libc::killpg(pid as libc::pid_t, signal) };
+    }
+}
+
+/// Waits for `child` to exit while streaming its output to `sinks`. With a
+/// `time_limit`, `child` must lead its own process group, which is stopped
+/// as a whole once the limit is reached.
+async fn monitor_process(
+    mut child: Child,
+    tail_lines: usize,
+    sinks: Vec<Box<dyn LineSink>>,
+    mut heartbeat: Option<Heartbeat>,
+    mut stall: Option<StallDetector>,
+    time_limit: Option<TimeLimit>,
+) -> Completion {
+    let capture = OutputCapture::start(&mut child, tail_lines, sinks);
+    let pid = child.id();
+    let mut stall_check = tokio::time::interval(stall::CHECK_INTERVAL);
+    let deadline = time_limit.map(|time_limit| tokio::time::Instant::now() + time_limit.limit);
+    let mut kill_at = None;
+    let mut timed_out = false;
+
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status,
+            _ = async { sleep_until(deadline.unwrap()).await }, if deadline.is_some() && !timed_out => {
+                let time_limit = time_limit.unwrap();
+                eprintln!(
+                    "Timed out after {}, sending SIGTERM.",
+                    format_duration(time_limit.limit)
+                );
+                signal_group(pid, libc::SIGTERM);
+                // A stopped group would only see the SIGTERM once continued.
+                signal_group(pid, libc::SIGCONT);
+                timed_out = true;
+                kill_at = Some(tokio::time::Instant::now() + time_limit.grace);
+            }
+            _ = async { sleep_until(kill_at.unwrap()).await }, if kill_at.is_some() => {
+                eprintln!("Still running after the grace period, sending SIGKILL.");
+                signal_group(pid, libc::SIGKILL);
+                kill_at = None;
+            }
+            // Ctrl-C only reaches the terminal's process group, which the child left.
+            Ok(()) = tokio::signal::ctrl_c(), if time_limit.is_some() => {
+                signal_group(pid, libc::SIGINT);
+            }
+            _ = async { heartbeat.as_ref().unwrap().wait().await }, if heartbeat.is_some() => {
+                let heartbeat = heartbeat.as_mut().unwrap();
+                let pids = pid.map(procfs::with_descendants).unwrap_or_default();
+                heartbeat.send(pid, &pids, capture.last_line()).await;
+            }
+            _ = stall_check.tick(), if stall.is_some() => {
+                let stall = stall.as_mut().unwrap();
+                stall.check_output(capture.last_activity()).await;
+            }
+        }
+    };
+    // The child's last output may still be in the pipes.
+    let output = capture.finish().await;
+    let status = match status {
         Ok(status) => {
-            if status.success() {
+            if timed_out {
+                eprintln!(
+                    "Process stopped after timing out: {}.",
+                    describe_timeout_status(&status)
+                );
+            } else if status.success() {
                 println!("Process finished successfully.");
             } else {
-                eprintln!("Process finished with an error.");
+                eprintln!(
+                    "Process finished with an error: {}.",
+                    describe_status(&status)
+                );
             }
+            Some(status)
         }
-        Err(e) => eprintln!("Error waiting for process to finish: {}", e),
+        Err(e) => {
+            eprintln!("Error waiting for process to finish: {}", e);
+            None
+        }
+    };
+    Completion {
+        status,
+        output,
+        timed_out,
     }
 }
 
-async fn monitor_process_by_pid(pid: u32, is_silent: Option<bool>) {
-    let wait_time = Duration::from_secs(1);
-    let is_silent = is_silent.unwrap_or(false);
-    let mut sp = if is_silent {
-        None
-    } else {
-        Some(Spinner::new(Spinners::Moon, format!("Monitoring PID: {}", pid)))
-    };
+async fn monitor_process_by_pid(
+    watch: ExitWatch,
+    mut heartbeat: Option<Heartbeat>,
+    mut stall: Option<StallDetector>,
+) {
+    let pid = watch.pid();
+    let command = procfs::cmdline(pid)
+        .filter(|args| !args.is_empty())
+        .map(|args| format!(" ({})", args.join(" ")))
+        .unwrap_or_default();
+    let mut sp = Spinner::new(
+        Spinners::Moon,
+        format!("Monitoring PID: {}{}", pid, command),
+    );
 
+    let mut tick = time::interval(Duration::from_secs(1));
     loop {
-        let status = StdCommand::new("pgrep ")
-            .arg(pid.to_string())
-            .output();
-        match status {
-            Ok(output) if !output.stdout.is_empty() => {
-                sleep(wait_time).await;
-            }
-            _ => {
-                if let Some(ref mut spinner) = sp {
-                    spinner.stop();
+        tokio::select! {
+            _ = watch.exited() => break,
+            _ = tick.tick(), if heartbeat.is_some() || stall.is_some() => {
+                if let Some(heartbeat) = heartbeat.as_mut().filter(|h| h.is_due()) {
+                    heartbeat.send(Some(pid), &[pid], None).await;
+                }
+                if let Some(stall) = &mut stall {
+                    stall.check_cpu(&[pid]).await;
                 }
-                println!("\nProcess with PID {} has terminated.", pid);
-                break;
             }
         }
     }
+    sp.stop();
+    println!("\nProcess with PID {} has terminated.", pid);
 }
 
-async fn monitor_process_by_name(process_name: &str) {
-    let wait_time = Duration::from_secs(1);
+/// Follows the processes `watcher` tracks, notifying about each one that
+/// exits and about all of them having finished once none is left. With
+/// `keep_watching` it then waits for new ones instead of returning.
+async fn monitor_process_by_name(
+    mut watcher: NameWatcher,
+    keep_watching: bool,
+    notifiers: Arc<NotifierRegistry>,
+    target: Target,
+    mut heartbeat: Option<Heartbeat>,
+    mut stall: Option<StallDetector>,
+) {
+    let name = target.value();
     let mut sp = Spinner::new(
         Spinners::Moon,
-        format!("Monitoring processes named: {}", process_name),
+        format!("Monitoring processes named: {}", name),
     );
+    let mut tick = time::interval(Duration::from_secs(1));
+    // When the current set of processes started being watched.
+    let mut since = (!watcher.pids().is_empty()).then(Instant::now);
     loop {
-        let status = StdCommand::new("pgrep").arg(process_name).output();
-        match status {
-            Ok(output) => {
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                let pids: Vec<u32> = output_str
-                    .lines()
-                    .filter_map(|line| line.trim().parse::<u32>().ok())
-                    .collect();
-                if pids.is_empty() {
-                    sp.stop();
-                    println!("\nAll processes named '{}' have terminated.", process_name);
-                    break;
-                } else {
-                    for pid in pids {
-                        let _ = task::spawn(monitor_process_by_pid(pid, Some(true)));
+        tokio::select! {
+            changes = watcher.changes() => {
+                for change in changes {
+                    match change {
+                        Change::Started(pid) => {
+                            println!("\nProcess with PID {} has started.", pid);
+                            if since.is_none() {
+                                since = Some(Instant::now());
+                                notifiers.notify(&Event::started(target.clone(), None)).await;
+                            }
+                        }
+                        Change::Exited { pid, status, runtime } => {
+                            match status {
+                                Some(status) => println!(
+                                    "\nProcess with PID {} has terminated: {}.",
+                                    pid,
+                                    describe_status(&status)
+                                ),
+                                None => println!("\nProcess with PID {} has terminated.", pid),
+                            }
+                            notifiers
+                                .notify(&Event::exited(target.clone(), pid, status, runtime))
+                                .await;
+                        }
+                    }
+                }
+                if let Some(started) = since.filter(|_| watcher.pids().is_empty()) {
+                    println!("\nAll processes named '{}' have terminated.", name);
+                    notifiers
+                        .notify(&Event::finished(target.clone(), None, None, started.elapsed()))
+                        .await;
+                    since = None;
+                    if !keep_watching {
+                        break;
                     }
                 }
             }
-            Err(_) => {
-                sp.stop();
-                println!("\nError retrieving process list.");
-                break;
+            _ = tick.tick(), if since.is_some() && (heartbeat.is_some() || stall.is_some()) => {
+                let pids = watcher.pids();
+                if let Some(heartbeat) = heartbeat.as_mut().filter(|h| h.is_due()) {
+                    heartbeat.send(None, &pids, None).await;
+                }
+                if let Some(stall) = &mut stall {
+                    stall.check_cpu(&pids).await;
+                }
             }
         }
-        sleep(wait_time).await;
     }
+    sp.stop();
 }
 
-async fn execute_and_monitor_command(command: &str) -> std::io::Result<Child> {
-    let child = TokioCommand::new("sh")
-        .arg("-c")
-        .arg(command)
-        .stdout(std::process::Stdio::piped())
-        .stderr(std::process::Stdio::piped())
-        .spawn()?;
-    println!("Started command '{}', PID: {:?}", command, child.id());
+/// The command `exec` runs: a program with its arguments, or a string for a shell.
+struct CommandLine {
+    args: Vec<String>,
+    shell: Option<String>,
+}
+
+impl CommandLine {
+    /// The command as a user would type it, for messages.
+    fn display(&self) -> String {
+        if self.shell.is_some() {
+            return self.args.join(" ");
+        }
+        let quoted: Vec<_> = self.args.iter().map(|arg| shell_quote(arg)).collect();
+        quoted.join(" ")
+    }
+
+    fn to_command(&self) -> TokioCommand {
+        match &self.shell {
+            Some(shell) => {
+                let mut cmd = TokioCommand::new(shell);
+                cmd.arg("-c").arg(self.args.join(" "));
+                cmd
+            }
+            None => {
+                let mut cmd = TokioCommand::new(&self.args[0]);
+                cmd.args(&self.args[1..]);
+                cmd
+            }
+        }
+    }
+}
+
+/// Quotes `arg` for a POSIX shell unless it is safe as it is.
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Spawns `command`, in a process group of its own if `own_group` is set.
+async fn execute_and_monitor_command(
+    command: &CommandLine,
+    own_group: bool,
+) -> std::io::Result<Child> {
+    let mut cmd = command.to_command();
+    cmd.stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    if own_group {
+        // A background process group reading the terminal would be stopped
+        // by SIGTTIN, so it gets no terminal input at all.
+        cmd.process_group(0).stdin(std::process::Stdio::null());
+    }
+    let child = cmd.spawn()?;
+    println!(
+        "Started command '{}', PID: {:?}",
+        command.display(),
+        child.id()
+    );
     Ok(child)
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
-    let bot_token = env::var("BOT_TOKEN").expect("BOT_TOKEN not set");
-    let chat_id = env::var("CHAT_ID").expect("CHAT_ID not set");
+    let notifiers = Config::load(cli.config)
+        .and_then(|mut config| {
+            if cli.desktop {
+                config.desktop.get_or_insert_with(Default::default);
+            }
+            config.notifiers()
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+    let notifiers = Arc::new(notifiers);
+    if notifiers.is_empty() {
+        eprintln!("No notifiers configured, reporting to the console only.");
+    } else if let Err(e) = notifiers.validate().await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+    let started_at = Instant::now();
+    let heartbeat = |target: &Target| {
+        cli.heartbeat
+            .map(|interval| Heartbeat::new(interval, notifiers.clone(), target.clone(), started_at))
+    };
+    let stall = |target: &Target, activity, pid| {
+        cli.stall_timeout.map(|timeout| {
+            StallDetector::new(timeout, activity, notifiers.clone(), target.clone(), pid)
+        })
+    };
 
-    match cli.command {
+    let code = match cli.command {
         Commands::Pid { pid } => {
-            send_telegram_message(
-                &bot_token,
-                &chat_id,
-                &format!("Starting to monitor PID: {}", pid),
-            )
-            .await;
-            monitor_process_by_pid(pid, None).await;
-            send_telegram_message(
-                &bot_token,
-                &chat_id,
-                &format!("Process {} has finished.", pid),
-            )
-            .await;
-        }
-        Commands::Name { process_name } => {
-            send_telegram_message(
-                &bot_token,
-                &chat_id,
-                &format!("Monitoring processes named: {}", process_name),
-            )
-            .await;
-            monitor_process_by_name(&process_name).await;
-            send_telegram_message(
-                &bot_token,
-                &chat_id,
-                &format!("Processes '{}' have finished.", process_name),
-            )
-            .await;
-        }
-        Commands::Exec { command } => {
-            send_telegram_message(
-                &bot_token,
-                &chat_id,
-                &format!("Starting command: '{}'", command),
-            )
-            .await;
-            match execute_and_monitor_command(&command).await {
-                Ok(child) => {
-                    let monitor_task = task::spawn(monitor_process(child));
-                    monitor_task.await.unwrap();
-                    send_telegram_message(
-                        &bot_token,
-                        &chat_id,
-                        &format!("Command '{}' has finished.", command),
+            // Record the process's start time right away, so it is not
+            // confused with a later process that gets the same PID.
+            match ExitWatch::new(pid) {
+                None => {
+                    eprintln!("No running process with PID {}.", pid);
+                    1
+                }
+                Some(watch) => {
+                    let target = Target::Pid(pid);
+                    notifiers
+                        .notify(&Event::started(target.clone(), Some(pid)))
+                        .await;
+                    monitor_process_by_pid(
+                        watch,
+                        heartbeat(&target),
+                        stall(&target, Activity::Cpu, Some(pid)),
                     )
                     .await;
+                    notifiers
+                        .notify(&Event::finished(
+                            target,
+                            Some(pid),
+                            None,
+                            started_at.elapsed(),
+                        ))
+                        .await;
+                    0
                 }
-                Err(e) => {
-                    eprintln!("Failed to execute command: {}", e);
+            }
+        }
+        Commands::Name {
+            process_name,
+            match_cmdline,
+            user,
+            parent,
+            exact,
+            keep_watching,
+        } => {
+            let mut matcher = Matcher {
+                name: process_name.clone(),
+                cmdline: match_cmdline,
+                user,
+                parent,
+            };
+            if exact {
+                matcher = matcher.exact();
+            }
+            let watcher = NameWatcher::new(matcher);
+            if watcher.pids().is_empty() && !keep_watching {
+                eprintln!("No matching process named '{}'.", process_name);
+                1
+            } else {
+                let target = Target::Name(process_name.to_string());
+                if !watcher.pids().is_empty() {
+                    notifiers
+                        .notify(&Event::started(target.clone(), None))
+                        .await;
                 }
+                monitor_process_by_name(
+                    watcher,
+                    keep_watching,
+                    notifiers.clone(),
+                    target.clone(),
+                    heartbeat(&target),
+                    stall(&target, Activity::Cpu, None),
+                )
+                .await;
+                0
             }
         }
+        Commands::Exec {
+            command,
+            shell,
+            tail_lines,
+            log,
+            alert,
+            progress,
+            timeout,
+            restart,
+        } => {
+            let command = CommandLine {
+                args: command,
+                shell,
+            };
+            let target = Target::Command(command.display());
+            let time_limit = timeout.limit();
+            let mut restarter = restart.options().map(Restarter::new);
+            let mut attempt_started = started_at;
+            loop {
+                let child = match execute_and_monitor_command(&command, time_limit.is_some()).await
+                {
+                    Ok(child) => child,
+                    Err(e) => {
+                        eprintln!("Failed to execute command: {}", e);
+                        let [program] = command.args.as_slice() else {
+                            break 1;
+                        };
+                        if command.shell.is_none() && program.contains(' ') {
+                            eprintln!("To run '{}' through a shell, pass --shell.", program);
+                        }
+                        break 1;
+                    }
+                };
+                let pid = child.id();
+                let mut sinks: Vec<Box<dyn LineSink>> = Vec::new();
+                let spool_path =
+                    env::temp_dir().join(format!("argus-{}-output.log", process::id()));
+                let spooled = notifiers.wants_output_file()
+                    && match OutputSpool::create(&spool_path) {
+                        Ok(spool) => {
+                            sinks.push(Box::new(spool));
+                            true
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to create {}: {}", spool_path.display(), e);
+                            false
+                        }
+                    };
+                let mut log_files = Vec::new();
+                if let Some(options) = log.options() {
+                    match OutputLog::create(options, pid) {
+                        Ok(output_log) => {
+                            log_files = output_log.paths();
+                            for path in &log_files {
+                                println!("Logging output to {}", path.display());
+                            }
+                            sinks.push(Box::new(output_log));
+                        }
+                        Err(e) => eprintln!("Failed to create output log: {}", e),
+                    }
+                }
+                let mut alerts = None;
+                if let Some(options) = alert.options() {
+                    let (sink, task) =
+                        alert::start(options, notifiers.clone(), target.clone(), pid);
+                    sinks.push(Box::new(sink));
+                    alerts = Some(task);
+                }
+                let mut progress_updates = None;
+                if let Some(options) = progress.options() {
+                    let (sink, task) = progress::start(options, notifiers.clone(), target.clone());
+                    sinks.push(Box::new(sink));
+                    progress_updates = Some(task);
+                }
+                // Restarts are announced by the notification about the previous run.
+                if restarter.as_ref().is_none_or(|r| r.restarts() == 0) {
+                    notifiers.notify(&Event::started(target.clone(), pid)).await;
+                }
+                let monitor_task = task::spawn(monitor_process(
+                    child,
+                    tail_lines,
+                    sinks,
+                    heartbeat(&target),
+                    stall(&target, Activity::Output, pid),
+                    time_limit,
+                ));
+                let Completion {
+                    status,
+                    output,
+                    timed_out,
+                } = monitor_task.await.unwrap();
+                if let Some(alerts) = alerts {
+                    // Deliver outstanding alerts before the finish notification.
+                    let _ = alerts.await;
+                }
+                if let Some(progress_updates) = progress_updates {
+                    let _ = progress_updates.await;
+                }
+
+                let duration = attempt_started.elapsed();
+                let failed = timed_out || !status.is_some_and(|status| status.success());
+                let decision = restarter
+                    .as_mut()
+                    .map_or(Decision::Stop, |r| r.next(failed, duration));
+                let event = match decision {
+                    Decision::Restart { restart, delay } => Event::restarting(
+                        target.clone(),
+                        pid,
+                        status,
+                        duration,
+                        restart,
+                        restarter.as_ref().and_then(Restarter::max_restarts),
+                        delay,
+                    ),
+                    Decision::GiveUp(reason) => {
+                        let restarts = restarter.as_ref().map_or(0, Restarter::restarts);
+                        Event::gave_up(target.clone(), pid, status, duration, restarts, reason)
+                    }
+                    Decision::Stop => match time_limit.filter(|_| timed_out) {
+                        Some(time_limit) => Event::timed_out(
+                            target.clone(),
+                            pid,
+                            status,
+                            duration,
+                            time_limit.limit,
+                        ),
+                        None => Event::finished(target.clone(), pid, status, duration),
+                    },
+                };
+                let mut event = event.with_output(output.tail).with_log_files(log_files);
+                if spooled && output.total_lines > event.output.len() {
+                    event = event.with_output_file(spool_path.clone());
+                }
+                if matches!(decision, Decision::Restart { .. } | Decision::GiveUp(_)) {
+                    eprintln!("{}", event.message());
+                }
+                notifiers.notify(&event).await;
+                if spooled {
+                    let _ = fs::remove_file(&spool_path);
+                }
+
+                if let Decision::Restart { delay, .. } = decision {
+                    sleep(delay).await;
+                    attempt_started = Instant::now();
+                    continue;
+                }
+                break if timed_out {
+                    TIMEOUT_EXIT_CODE
+                } else {
+                    status.as_ref().map(exit_code).unwrap_or(1)
+                };
+            }
+        }
+    };
+    std::process::exit(code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_safe_arguments_alone() {
+        assert_eq!(shell_quote("make"), "make");
+        assert_eq!(shell_quote("--jobs=4"), "--jobs=4");
+        assert_eq!(shell_quote("./a/b.txt"), "./a/b.txt");
+        assert_eq!(shell_quote("user@host:1,2+3%"), "user@host:1,2+3%");
+    }
+
+    #[test]
+    fn quotes_everything_else() {
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("two words"), "'two words'");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+        assert_eq!(shell_quote("a;b"), "'a;b'");
+        assert_eq!(shell_quote("naïve"), "'naïve'");
+    }
+
+    #[test]
+    fn escapes_single_quotes() {
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("'"), "''\\'''");
     }
 }
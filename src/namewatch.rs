@@ -0,0 +1,246 @@
+//! Keeping track of the processes `name` looks for.
+//!
+//! Process events from the kernel are used when argus may subscribe to them,
+//! otherwise `/proc` is scanned periodically. Both report the same changes.
+
+use regex::Regex;
+use std::{collections::HashMap, ffi::CString, io, process::ExitStatus, time::Duration};
+use tokio::time::{sleep_until, Instant};
+
+use crate::{
+    procevents::{ProcEvent, ProcEvents},
+    procfs::{self, Stat},
+};
+
+/// How often `/proc` is scanned without process events.
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a forked child that has not exec'd yet must keep matching before
+/// it counts. Until it execs it carries its parent's name, and most children
+/// of shells and build tools exec something else right away.
+const FORK_GRACE: Duration = Duration::from_secs(1);
+
+/// Parses a user name or numeric UID into a UID.
+pub fn parse_user(text: &str) -> Result<u32, String> {
+    if let Ok(uid) = text.parse() {
+        return Ok(uid);
+    }
+    let name = CString::new(text).map_err(|e| e.to_string())?;
+    // SAFETY: the name is NUL-terminated. The returned entry is only read
+    // before any other getpw* call could overwrite it.
+    let entry = unsafe { libc::getpwnam(name.as_ptr()) };
+    if entry.is_null() {
+        return Err(format!("no user named '{}'", text));
+    }
+    Ok(unsafe { (*entry).pw_uid })
+}
+
+/// What a process must look like to be watched.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    /// Matched against the name the kernel keeps, cut to 15 bytes.
+    pub name: Regex,
+    /// Matched against the arguments joined by spaces.
+    pub cmdline: Option<Regex>,
+    /// Effective user ID.
+    pub user: Option<u32>,
+    pub parent: Option<u32>,
+}
+
+impl Matcher {
+    /// Requires `name` to match the whole process name, like `pgrep -x`.
+    pub fn exact(mut self) -> Self {
+        self.name = Regex::new(&format!("^(?:{})$", self.name.as_str()))
+            .expect("anchoring a valid regex keeps it valid");
+        self
+    }
+
+    /// Checks the cheap fields from `stat` first and reads the rest only if needed.
+    fn matches(&self, pid: u32, stat: &Stat) -> bool {
+        self.name.is_match(&stat.name)
+            && self.parent.is_none_or(|parent| stat.parent == parent)
+            && self.user.is_none_or(|user| procfs::uid(pid) == Some(user))
+            && self.cmdline.as_ref().is_none_or(|cmdline| {
+                procfs::cmdline(pid).is_some_and(|args| cmdline.is_match(&args.join(" ")))
+            })
+    }
+}
+
+/// A change to the set of matching processes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Started(u32),
+    /// The process exited, or exec'd a program that does not match.
+    /// The exit status is only known from process events.
+    Exited {
+        pid: u32,
+        status: Option<ExitStatus>,
+        runtime: Option<Duration>,
+    },
+}
+
+pub struct NameWatcher {
+    matcher: Matcher,
+    /// The matching processes and when they started, relative to boot. A
+    /// different start time means the PID was reused by another process.
+    pids: HashMap<u32, Duration>,
+    /// Forked children that matched so far, with when to confirm them.
+    pending: HashMap<u32, Instant>,
+    events: Option<ProcEvents>,
+    next_scan: Instant,
+}
+
+impl NameWatcher {
+    pub fn new(matcher: Matcher) -> Self {
+        // Subscribe before the first scan so no process slips through in between.
+        let events = ProcEvents::subscribe().ok();
+        let mut watcher = Self {
+            matcher,
+            pids: HashMap::new(),
+            pending: HashMap::new(),
+            events,
+            next_scan: Instant::now() + SCAN_INTERVAL,
+        };
+        watcher.rescan();
+        watcher
+    }
+
+    /// The matching processes, as of the last change.
+    pub fn pids(&self) -> Vec<u32> {
+        self.pids.keys().copied().collect()
+    }
+
+    /// Waits for the set of matching processes to change. Cancelling the
+    /// returned future loses no changes.
+    pub async fn changes(&mut self) -> Vec<Change> {
+        loop {
+            let changes = match &self.events {
+                Some(events) => {
+                    let confirm_at = self.pending.values().min().copied();
+                    let received = tokio::select! {
+                        received = events.next() => Some(received),
+                        _ = sleep_until(confirm_at.unwrap_or_else(Instant::now)),
+                            if confirm_at.is_some() => None,
+                    };
+                    match received {
+                        Some(Ok(events)) => self.apply(events),
+                        Some(Err(e)) => self.lose_events(e),
+                        None => self.confirm_pending(),
+                    }
+                }
+                None => {
+                    sleep_until(self.next_scan).await;
+                    self.next_scan = Instant::now() + SCAN_INTERVAL;
+                    self.rescan()
+                }
+            };
+            if !changes.is_empty() {
+                return changes;
+            }
+        }
+    }
+
+    fn lose_events(&mut self, error: io::Error) -> Vec<Change> {
+        eprintln!("Lost process events, scanning /proc instead: {}", error);
+        self.events = None;
+        self.rescan()
+    }
+
+    /// Whether `pid` is a process other than argus that matches right now.
+    fn current_match(&self, pid: u32) -> Option<Stat> {
+        procfs::stat(pid).filter(|stat| {
+            pid != std::process::id() && stat.is_running() && self.matcher.matches(pid, stat)
+        })
+    }
+
+    /// Starts tracking forked children that kept matching for [`FORK_GRACE`].
+    fn confirm_pending(&mut self) -> Vec<Change> {
+        let now = Instant::now();
+        let due: Vec<u32> = self
+            .pending
+            .iter()
+            .filter(|&(_, &confirm_at)| confirm_at <= now)
+            .map(|(&pid, _)| pid)
+            .collect();
+        let mut changes = Vec::new();
+        for pid in due {
+            self.pending.remove(&pid);
+            if let Some(stat) = self.current_match(pid) {
+                if self.pids.insert(pid, stat.start_time).is_none() {
+                    changes.push(Change::Started(pid));
+                }
+            }
+        }
+        changes
+    }
+
+    /// Stops tracking `pid`, returning the change to report if it was tracked.
+    fn remove(&mut self, pid: u32, status: Option<ExitStatus>) -> Option<Change> {
+        let start_time = self.pids.remove(&pid)?;
+        let runtime = procfs::uptime().and_then(|now| now.checked_sub(start_time));
+        Some(Change::Exited {
+            pid,
+            status,
+            runtime,
+        })
+    }
+
+    fn apply(&mut self, events: Vec<ProcEvent>) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for event in events {
+            match event {
+                ProcEvent::Fork { child } => {
+                    if self.current_match(child).is_some() {
+                        self.pending.insert(child, Instant::now() + FORK_GRACE);
+                    }
+                }
+                ProcEvent::Exec { pid } => {
+                    self.pending.remove(&pid);
+                    match self.current_match(pid) {
+                        Some(stat) => {
+                            if self.pids.insert(pid, stat.start_time).is_none() {
+                                changes.push(Change::Started(pid));
+                            }
+                        }
+                        None => changes.extend(self.remove(pid, None)),
+                    }
+                }
+                ProcEvent::Exit { pid, status } => {
+                    // A child that never got past the grace period was never reported.
+                    if self.pending.remove(&pid).is_none() {
+                        changes.extend(self.remove(pid, Some(status)));
+                    }
+                }
+                ProcEvent::Overrun => changes.extend(self.rescan()),
+            }
+        }
+        changes
+    }
+
+    /// Compares the process table with the known set.
+    fn rescan(&mut self) -> Vec<Change> {
+        let current: HashMap<u32, Duration> =
+            procfs::find(|pid, stat| self.matcher.matches(pid, stat))
+                .into_iter()
+                .map(|(pid, stat)| (pid, stat.start_time))
+                .collect();
+        let gone: Vec<u32> = self
+            .pids
+            .iter()
+            .filter(|&(pid, start_time)| current.get(pid) != Some(start_time))
+            .map(|(&pid, _)| pid)
+            .collect();
+        let mut changes: Vec<Change> = gone
+            .into_iter()
+            .filter_map(|pid| self.remove(pid, None))
+            .collect();
+        for (pid, start_time) in current {
+            if self.pids.insert(pid, start_time).is_none() {
+                changes.push(Change::Started(pid));
+            }
+        }
+        // Anything still matching is tracked now, the rest is gone.
+        self.pending.clear();
+        changes
+    }
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use notify_rust::{Notification, Timeout, Urgency};
+use std::time::Duration;
+use tokio::task;
+
+use super::{Event, EventKind, Notifier, NotifyError, Severity};
+
+/// Shows events as popups through the `org.freedesktop.Notifications`
+/// service on the D-Bus session bus.
+pub struct DesktopNotifier {
+    timeout: Option<Duration>,
+}
+
+impl DesktopNotifier {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self { timeout }
+    }
+
+    fn urgency(event: &Event) -> Urgency {
+        match (&event.kind, event.severity()) {
+            (EventKind::Started | EventKind::Heartbeat { .. }, _) => Urgency::Low,
+            (_, Severity::Failure) => Urgency::Critical,
+            _ => Urgency::Normal,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for DesktopNotifier {
+    fn name(&self) -> &str {
+        "desktop"
+    }
+
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        let mut notification = Notification::new();
+        notification
+            .appname("argus")
+            .summary(&event.title())
+            .body(&event.message())
+            .icon(match event.severity() {
+                Severity::Info | Severity::Success => "dialog-information",
+                Severity::Warning => "dialog-warning",
+                Severity::Failure => "dialog-error",
+            })
+            .urgency(Self::urgency(event));
+        if let Some(timeout) = self.timeout {
+            notification.timeout(Timeout::Milliseconds(timeout.as_millis() as u32));
+        }
+        // The D-Bus call blocks, keep it off the runtime's worker threads.
+        task::spawn_blocking(move || notification.show()).await??;
+        Ok(())
+    }
+}
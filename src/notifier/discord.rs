@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+use reqwest::{header::RETRY_AFTER, Client};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::{
+    hostname, send_with_retries, truncate, Event, Notifier, NotifyError, Severity, Target,
+};
+
+const MAX_ATTEMPTS: u32 = 5;
+/// Discord rejects embed field values longer than this.
+const FIELD_VALUE_LIMIT: usize = 1024;
+const DESCRIPTION_LIMIT: usize = 4096;
+
+const COLOR_INFO: u32 = 0x5865f2;
+const COLOR_SUCCESS: u32 = 0x57f287;
+const COLOR_WARNING: u32 = 0xfee75c;
+const COLOR_FAILURE: u32 = 0xed4245;
+
+/// Posts events to a Discord channel webhook as embeds.
+pub struct DiscordNotifier {
+    client: Client,
+    webhook_url: String,
+}
+
+impl DiscordNotifier {
+    pub fn new(webhook_url: impl Into<String>) -> Self {
+        Self {
+            client: Client::new(),
+            webhook_url: webhook_url.into(),
+        }
+    }
+
+    fn payload(event: &Event) -> Value {
+        let mut details = event.fields().into_iter();
+        let (label, value) = details.next().expect("fields start with the target");
+        let value = match event.target {
+            Target::Pid(_) => value,
+            _ => format!("`{}`", value),
+        };
+        let mut fields = vec![field(label, &value, false), field("Host", hostname(), true)];
+        fields.extend(details.map(|(label, value)| field(label, &value, true)));
+        let color = match event.severity() {
+            Severity::Info => COLOR_INFO,
+            Severity::Success => COLOR_SUCCESS,
+            Severity::Warning => COLOR_WARNING,
+            Severity::Failure => COLOR_FAILURE,
+        };
+
+        let mut description = event.message();
+        let room = DESCRIPTION_LIMIT.saturating_sub(description.chars().count() + 16);
+        if let Some(output) = event.output_text(room) {
+            // A zero-width space keeps the output from closing the code block.
+            let output = output.replace("```", "`\u{200b}``");
+            description.push_str(&format!("\n```\n{}\n```", output));
+        }
+
+        json!({
+            "embeds": [{
+                "title": event.title(),
+                "description": truncate(&description, DESCRIPTION_LIMIT),
+                "color": color,
+                "fields": fields,
+            }],
+        })
+    }
+}
+
+fn field(name: &str, value: &str, inline: bool) -> Value {
+    json!({ "name": name, "value": truncate(value, FIELD_VALUE_LIMIT), "inline": inline })
+}
+
+/// How long Discord asked us to wait before retrying, from the `Retry-After`
+/// header (seconds, possibly fractional).
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let secs: f64 = response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &str {
+        "Discord"
+    }
+
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        let payload = Self::payload(event);
+        send_with_retries(
+            MAX_ATTEMPTS,
+            || self.client.post(&self.webhook_url).json(&payload),
+            |response| async move { retry_after(&response) },
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::testing::serve_once;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn retries_after_rate_limit() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}/api/webhooks/1/secret",
+            listener.local_addr().unwrap()
+        );
+        let notifier = DiscordNotifier::new(url);
+        let event = Event::started(Target::Pid(42), Some(42));
+
+        let server = async {
+            serve_once(
+                &listener,
+                "429 Too Many Requests",
+                &[("Retry-After", "0.1")],
+            )
+            .await;
+            serve_once(&listener, "204 No Content", &[]).await
+        };
+        let (result, body) = tokio::join!(notifier.notify(&event), server);
+        result.unwrap();
+
+        let payload: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["embeds"][0]["title"], event.title());
+    }
+}
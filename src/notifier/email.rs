@@ -0,0 +1,279 @@
+use async_trait::async_trait;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use serde::Deserialize;
+
+use super::{describe_status, escape_html, hostname, Event, EventKind, Notifier, NotifyError};
+
+/// How the connection to the SMTP server is secured.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection with STARTTLS (port 587).
+    #[default]
+    Starttls,
+    /// TLS from the first byte (port 465).
+    Tls,
+    /// Unencrypted, e.g. a local relay or test sink.
+    None,
+}
+
+/// Emails the owner when a monitored target finishes.
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    pub fn new(
+        host: &str,
+        port: Option<u16>,
+        security: SmtpSecurity,
+        credentials: Option<(String, String)>,
+        from: &str,
+        to: &[String],
+    ) -> Result<Self, NotifyError> {
+        let mut builder = match security {
+            SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        };
+        if let Some(port) = port {
+            builder = builder.port(port);
+        }
+        if let Some((username, password)) = credentials {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from: from.parse()?,
+            to: to
+                .iter()
+                .map(|address| address.parse())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn subject(event: &Event) -> String {
+        match &event.kind {
+            EventKind::Finished {
+                status: Some(status),
+                ..
+            }
+            | EventKind::Exited {
+                status: Some(status),
+                ..
+            } => format!(
+                "[argus] {} on {}: {}",
+                event.title(),
+                hostname(),
+                describe_status(status)
+            ),
+            _ => format!("[argus] {} on {}", event.title(), hostname()),
+        }
+    }
+
+    fn details(event: &Event) -> Vec<(&'static str, String)> {
+        let mut details = event.fields();
+        details.insert(1, ("Host", hostname().to_string()));
+        details
+    }
+
+    fn output_heading(event: &Event) -> String {
+        match event.kind {
+            EventKind::Alert { .. } => "Context".to_string(),
+            _ => format!("Last {} lines of output", event.output.len()),
+        }
+    }
+
+    fn plain_body(event: &Event) -> String {
+        let mut body = format!("{}\n\n", event.message());
+        for (label, value) in Self::details(event) {
+            body.push_str(&format!("{}: {}\n", label, value));
+        }
+        if !event.output.is_empty() {
+            body.push_str(&format!("\n{}:\n", Self::output_heading(event)));
+            for line in &event.output {
+                body.push_str(line);
+                body.push('\n');
+            }
+        }
+        body
+    }
+
+    fn html_body(event: &Event) -> String {
+        let mut body = format!("<p>{}</p>\n<table>\n", escape_html(&event.message()));
+        for (label, value) in Self::details(event) {
+            body.push_str(&format!(
+                "<tr><th align=\"left\">{}</th><td>{}</td></tr>\n",
+                label,
+                escape_html(&value)
+            ));
+        }
+        body.push_str("</table>\n");
+        if !event.output.is_empty() {
+            body.push_str(&format!(
+                "<p>{}:</p>\n<pre>{}</pre>\n",
+                Self::output_heading(event),
+                escape_html(&event.output.join("\n"))
+            ));
+        }
+        body
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        // Mail is for results and for things that need attention; anything
+        // sent while the target runs normally would flood the inbox.
+        if !matches!(
+            event.kind,
+            EventKind::Finished { .. }
+                | EventKind::GaveUp { .. }
+                | EventKind::Alert { .. }
+                | EventKind::Stalled { .. }
+        ) {
+            return Ok(());
+        }
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(Self::subject(event));
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message.multipart(MultiPart::alternative_plain_html(
+            Self::plain_body(event),
+            Self::html_body(event),
+        ))?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::{Activity, Target};
+    use std::{os::unix::process::ExitStatusExt, process::ExitStatus, time::Duration};
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    /// What an SMTP client handed over in one session.
+    #[derive(Default)]
+    struct Received {
+        commands: Vec<String>,
+        data: String,
+    }
+
+    /// Accepts one SMTP session and records it, accepting every command.
+    async fn smtp_sink(listener: TcpListener) -> Received {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let mut received = Received::default();
+        writer.write_all(b"220 sink ready\r\n").await.unwrap();
+        let mut in_data = false;
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    writer.write_all(b"250 queued\r\n").await.unwrap();
+                } else {
+                    received.data.push_str(&line);
+                    received.data.push('\n');
+                }
+                continue;
+            }
+            let verb = line.split(' ').next().unwrap_or_default().to_uppercase();
+            received.commands.push(line);
+            let reply: &[u8] = match verb.as_str() {
+                "DATA" => {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                }
+                "QUIT" => b"221 bye\r\n",
+                _ => b"250 ok\r\n",
+            };
+            writer.write_all(reply).await.unwrap();
+            if verb == "QUIT" {
+                break;
+            }
+        }
+        received
+    }
+
+    fn notifier(port: u16) -> EmailNotifier {
+        EmailNotifier::new(
+            "127.0.0.1",
+            Some(port),
+            SmtpSecurity::None,
+            None,
+            "argus <argus@example.com>",
+            &["owner@example.com".to_string()],
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn sends_mail_to_local_sink() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let notifier = notifier(listener.local_addr().unwrap().port());
+        let event = Event::finished(
+            Target::Command("make".into()),
+            Some(42),
+            Some(ExitStatus::from_raw(2 << 8)),
+            Duration::from_secs(3),
+        )
+        .with_output(vec!["error: oops".into()]);
+
+        let (result, received) = tokio::join!(notifier.notify(&event), smtp_sink(listener));
+        result.unwrap();
+
+        assert!(received
+            .commands
+            .iter()
+            .any(|command| command == "MAIL FROM:<argus@example.com>"));
+        assert!(received
+            .commands
+            .iter()
+            .any(|command| command == "RCPT TO:<owner@example.com>"));
+        assert!(received.data.contains("Subject: [argus] "));
+        assert!(received.data.contains("multipart/alternative"));
+        assert!(received.data.contains("Last 1 lines of output:"));
+        assert!(received.data.contains("error: oops"));
+    }
+
+    #[tokio::test]
+    async fn skips_routine_events() {
+        // Nothing listens on the port, so any attempt to send would fail.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let notifier = notifier(port);
+        let target = Target::Name("worker".into());
+        let events = [
+            Event::started(target.clone(), Some(42)),
+            Event::exited(target.clone(), 42, None, None),
+            Event::resumed(target, Some(42), Activity::Cpu, Duration::from_secs(60)),
+        ];
+        for event in &events {
+            notifier.notify(event).await.unwrap();
+        }
+        let finished = Event::finished(Target::Pid(42), Some(42), None, Duration::from_secs(1));
+        assert!(notifier.notify(&finished).await.is_err());
+    }
+}
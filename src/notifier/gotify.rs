@@ -0,0 +1,61 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+
+use super::{Event, Notifier, NotifyError, Severity};
+
+/// Keeps push notifications readable; Gotify itself has no hard limit.
+const MESSAGE_LIMIT: usize = 4096;
+
+/// Pushes events to a Gotify server as an application.
+pub struct GotifyNotifier {
+    client: Client,
+    server_url: String,
+    app_token: String,
+    priority: u8,
+    failure_priority: u8,
+}
+
+impl GotifyNotifier {
+    pub fn new(
+        server_url: impl Into<String>,
+        app_token: impl Into<String>,
+        priority: u8,
+        failure_priority: u8,
+    ) -> Self {
+        Self {
+            client: Client::new(),
+            server_url: server_url.into(),
+            app_token: app_token.into(),
+            priority,
+            failure_priority,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for GotifyNotifier {
+    fn name(&self) -> &str {
+        "Gotify"
+    }
+
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        let priority = match event.severity() {
+            Severity::Info | Severity::Success => self.priority,
+            Severity::Warning | Severity::Failure => self.failure_priority,
+        };
+        let url = format!("{}/message", self.server_url.trim_end_matches('/'));
+        self.client
+            .post(&url)
+            .header("X-Gotify-Key", &self.app_token)
+            .json(&json!({
+                "title": event.title(),
+                "message": event.message_with_output(MESSAGE_LIMIT),
+                "priority": priority,
+            }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())?;
+        Ok(())
+    }
+}
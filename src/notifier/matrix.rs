@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use serde_json::{json, Value};
+use std::{
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::{escape_html, hostname, send_with_retries, Event, Notifier, NotifyError};
+
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Sends events to a Matrix room through the client-server API.
+pub struct MatrixNotifier {
+    client: Client,
+    homeserver: Url,
+    access_token: String,
+    room_id: String,
+    /// Prefix making transaction IDs unique across argus invocations.
+    txn_prefix: String,
+    txn_counter: AtomicU64,
+}
+
+impl MatrixNotifier {
+    pub fn new(
+        homeserver: &str,
+        access_token: impl Into<String>,
+        room_id: impl Into<String>,
+    ) -> Result<Self, NotifyError> {
+        let homeserver = Url::parse(homeserver)?;
+        if homeserver.cannot_be_a_base() {
+            return Err(format!("{} is not a valid homeserver URL", homeserver).into());
+        }
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        Ok(Self {
+            client: Client::new(),
+            homeserver,
+            access_token: access_token.into(),
+            room_id: room_id.into(),
+            txn_prefix: format!("argus-{}-{}", process::id(), started),
+            txn_counter: AtomicU64::new(0),
+        })
+    }
+
+    fn send_url(&self, txn_id: &str) -> Url {
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .expect("checked in MatrixNotifier::new")
+            .pop_if_empty()
+            .extend([
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                &self.room_id,
+                "send",
+                "m.room.message",
+                txn_id,
+            ]);
+        url
+    }
+
+    fn content(event: &Event) -> Value {
+        let mut html = format!(
+            "<p><strong>{}</strong> on <code>{}</code></p><p>{}</p>",
+            escape_html(&event.title()),
+            escape_html(hostname()),
+            escape_html(&event.message())
+        );
+        html.push_str("<ul>");
+        for (label, value) in event.fields() {
+            html.push_str(&format!(
+                "<li><strong>{}:</strong> <code>{}</code></li>",
+                label,
+                escape_html(&value)
+            ));
+        }
+        html.push_str("</ul>");
+        if !event.output.is_empty() {
+            html.push_str(&format!(
+                "<pre><code>{}</code></pre>",
+                escape_html(&event.output.join("\n"))
+            ));
+        }
+
+        json!({
+            "msgtype": "m.text",
+            "body": event.message(),
+            "format": "org.matrix.custom.html",
+            "formatted_body": html,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &str {
+        "Matrix"
+    }
+
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        // The same transaction ID is reused for every retry so the homeserver
+        // deduplicates a message whose response got lost.
+        let txn = self.txn_counter.fetch_add(1, Ordering::Relaxed);
+        let url = self.send_url(&format!("{}-{}", self.txn_prefix, txn));
+        let content = Self::content(event);
+
+        send_with_retries(
+            MAX_ATTEMPTS,
+            || {
+                self.client
+                    .put(url.clone())
+                    .bearer_auth(&self.access_token)
+                    .json(&content)
+            },
+            |response| async move {
+                let body: Value = response.json().await.ok()?;
+                body["retry_after_ms"].as_u64().map(Duration::from_millis)
+            },
+        )
+        .await
+    }
+}
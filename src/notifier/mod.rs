@@ -0,0 +1,1089 @@
+//! Notification backends and the registry that fans lifecycle events out to them.
+
+mod desktop;
+mod discord;
+mod email;
+mod gotify;
+mod matrix;
+mod ntfy;
+mod slack;
+mod telegram;
+#[cfg(test)]
+mod testing;
+mod webhook;
+
+pub use desktop::DesktopNotifier;
+pub use discord::DiscordNotifier;
+pub use email::{EmailNotifier, SmtpSecurity};
+pub use gotify::GotifyNotifier;
+pub use matrix::MatrixNotifier;
+pub use ntfy::NtfyNotifier;
+pub use slack::SlackNotifier;
+pub use telegram::{OutputUpload, TelegramNotifier, DEFAULT_API_URL as TELEGRAM_API_URL};
+pub use webhook::WebhookNotifier;
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::{
+    ffi::CStr,
+    future::Future,
+    os::unix::process::ExitStatusExt,
+    path::PathBuf,
+    process::ExitStatus,
+    sync::OnceLock,
+    time::{Duration, SystemTime},
+};
+use tokio::time::sleep;
+
+pub type NotifyError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Longest wait before a retry. A rate limit may ask for far more, which
+/// would hold up the notifications of every other backend.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// What a monitoring session is watching.
+#[derive(Debug, Clone)]
+pub enum Target {
+    Pid(u32),
+    Name(String),
+    Command(String),
+}
+
+impl Target {
+    /// Field label for the target, e.g. in a chat message attachment.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Target::Pid(_) => "PID",
+            Target::Name(_) => "Process name",
+            Target::Command(_) => "Command",
+        }
+    }
+
+    pub fn value(&self) -> String {
+        match self {
+            Target::Pid(pid) => pid.to_string(),
+            Target::Name(name) => name.clone(),
+            Target::Command(command) => command.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum EventKind {
+    Started,
+    /// `status` is only known for processes argus spawned itself.
+    Finished {
+        status: Option<ExitStatus>,
+        duration: Duration,
+        /// Set to the time limit if argus stopped the target for exceeding it.
+        timed_out: Option<Duration>,
+    },
+    /// One of several processes matched by name exited while others may
+    /// still run. `status` is only known from kernel process events and
+    /// `duration` is the process's own runtime, if known.
+    Exited {
+        status: Option<ExitStatus>,
+        duration: Option<Duration>,
+    },
+    /// A line of output matched an alert pattern. `suppressed` counts the
+    /// matches swallowed by the cool-down since the previous alert.
+    Alert {
+        pattern: String,
+        line: String,
+        suppressed: usize,
+    },
+    /// Periodic sign of life of a target that is still running.
+    Heartbeat {
+        elapsed: Duration,
+        usage: ResourceUsage,
+        /// Most recent line of output, for commands argus spawned itself.
+        last_line: Option<String>,
+    },
+    /// The target showed no sign of `activity` for `idle`.
+    Stalled {
+        activity: Activity,
+        idle: Duration,
+    },
+    /// The target became active again after having stalled for `idle`.
+    Resumed {
+        activity: Activity,
+        idle: Duration,
+    },
+    /// A supervised command exited and is started again after `delay`.
+    /// `restart` counts from 1, up to `max_restarts` if there is a limit.
+    Restarting {
+        status: Option<ExitStatus>,
+        duration: Duration,
+        restart: usize,
+        max_restarts: Option<usize>,
+        delay: Duration,
+    },
+    /// A supervised command exited and will not be restarted any more.
+    GaveUp {
+        status: Option<ExitStatus>,
+        duration: Duration,
+        restarts: usize,
+        reason: GiveUpReason,
+    },
+}
+
+/// Why a supervised command is no longer restarted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GiveUpReason {
+    /// It was restarted as often as allowed.
+    RetriesExhausted,
+    /// It failed `failures` times within `window`.
+    CrashLoop { failures: usize, window: Duration },
+}
+
+impl GiveUpReason {
+    pub fn describe(&self) -> String {
+        match self {
+            GiveUpReason::RetriesExhausted => "restart limit reached".to_string(),
+            GiveUpReason::CrashLoop { failures, window } => format!(
+                "crash loop, {} failures within {}",
+                failures,
+                format_duration(*window)
+            ),
+        }
+    }
+}
+
+/// What counts as a target making progress for stall detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activity {
+    /// The command printed something.
+    Output,
+    /// The process used CPU time.
+    Cpu,
+}
+
+impl Activity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Activity::Output => "output",
+            Activity::Cpu => "cpu",
+        }
+    }
+}
+
+/// CPU and memory use of the monitored process(es), where known.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceUsage {
+    /// Share of one CPU core, so busy multi-threaded processes exceed 100.
+    pub cpu_percent: Option<f64>,
+    /// Resident memory in bytes.
+    pub memory: Option<u64>,
+}
+
+/// How an event should be presented, e.g. its colour or priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Failure,
+}
+
+/// A lifecycle event emitted while monitoring a target.
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub target: Target,
+    pub pid: Option<u32>,
+    pub kind: EventKind,
+    /// Most recent lines of the target's output, oldest first, if captured.
+    pub output: Vec<String>,
+    /// Complete captured output when `output` only holds its tail.
+    pub output_file: Option<PathBuf>,
+    /// Files holding the target's full output, if it is being logged.
+    pub log_files: Vec<PathBuf>,
+    /// When the event happened.
+    pub timestamp: SystemTime,
+}
+
+impl Event {
+    fn new(target: Target, pid: Option<u32>, kind: EventKind) -> Self {
+        Self {
+            target,
+            pid,
+            kind,
+            output: Vec::new(),
+            output_file: None,
+            log_files: Vec::new(),
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    pub fn started(target: Target, pid: Option<u32>) -> Self {
+        Self::new(target, pid, EventKind::Started)
+    }
+
+    pub fn finished(
+        target: Target,
+        pid: Option<u32>,
+        status: Option<ExitStatus>,
+        duration: Duration,
+    ) -> Self {
+        let kind = EventKind::Finished {
+            status,
+            duration,
+            timed_out: None,
+        };
+        Self::new(target, pid, kind)
+    }
+
+    /// A command that argus stopped after it ran for longer than `limit`.
+    pub fn timed_out(
+        target: Target,
+        pid: Option<u32>,
+        status: Option<ExitStatus>,
+        duration: Duration,
+        limit: Duration,
+    ) -> Self {
+        let kind = EventKind::Finished {
+            status,
+            duration,
+            timed_out: Some(limit),
+        };
+        Self::new(target, pid, kind)
+    }
+
+    pub fn exited(
+        target: Target,
+        pid: u32,
+        status: Option<ExitStatus>,
+        duration: Option<Duration>,
+    ) -> Self {
+        Self::new(target, Some(pid), EventKind::Exited { status, duration })
+    }
+
+    pub fn alert(
+        target: Target,
+        pid: Option<u32>,
+        pattern: String,
+        line: String,
+        suppressed: usize,
+    ) -> Self {
+        let kind = EventKind::Alert {
+            pattern,
+            line,
+            suppressed,
+        };
+        Self::new(target, pid, kind)
+    }
+
+    pub fn heartbeat(
+        target: Target,
+        pid: Option<u32>,
+        elapsed: Duration,
+        usage: ResourceUsage,
+        last_line: Option<String>,
+    ) -> Self {
+        let kind = EventKind::Heartbeat {
+            elapsed,
+            usage,
+            last_line,
+        };
+        Self::new(target, pid, kind)
+    }
+
+    pub fn stalled(target: Target, pid: Option<u32>, activity: Activity, idle: Duration) -> Self {
+        Self::new(target, pid, EventKind::Stalled { activity, idle })
+    }
+
+    pub fn resumed(target: Target, pid: Option<u32>, activity: Activity, idle: Duration) -> Self {
+        Self::new(target, pid, EventKind::Resumed { activity, idle })
+    }
+
+    pub fn restarting(
+        target: Target,
+        pid: Option<u32>,
+        status: Option<ExitStatus>,
+        duration: Duration,
+        restart: usize,
+        max_restarts: Option<usize>,
+        delay: Duration,
+    ) -> Self {
+        let kind = EventKind::Restarting {
+            status,
+            duration,
+            restart,
+            max_restarts,
+            delay,
+        };
+        Self::new(target, pid, kind)
+    }
+
+    pub fn gave_up(
+        target: Target,
+        pid: Option<u32>,
+        status: Option<ExitStatus>,
+        duration: Duration,
+        restarts: usize,
+        reason: GiveUpReason,
+    ) -> Self {
+        let kind = EventKind::GaveUp {
+            status,
+            duration,
+            restarts,
+            reason,
+        };
+        Self::new(target, pid, kind)
+    }
+
+    pub fn with_output(mut self, output: Vec<String>) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn with_output_file(mut self, output_file: PathBuf) -> Self {
+        self.output_file = Some(output_file);
+        self
+    }
+
+    pub fn with_log_files(mut self, log_files: Vec<PathBuf>) -> Self {
+        self.log_files = log_files;
+        self
+    }
+
+    /// The captured output joined into one block of at most `max` characters.
+    /// When it does not fit, the oldest part is dropped.
+    pub fn output_text(&self, max: usize) -> Option<String> {
+        if self.output.is_empty() {
+            return None;
+        }
+        let text = self.output.join("\n");
+        let len = text.chars().count();
+        if len <= max {
+            return Some(text);
+        }
+        let mut short = String::from("…");
+        short.extend(text.chars().skip(len - max.saturating_sub(1)));
+        Some(short)
+    }
+
+    /// `message()` followed by the captured output, within `max` characters.
+    pub fn message_with_output(&self, max: usize) -> String {
+        let message = self.message();
+        let header = match self.kind {
+            EventKind::Alert { .. } => "\n\nContext:\n".to_string(),
+            _ => format!("\n\nLast {} lines of output:\n", self.output.len()),
+        };
+        let room = max.saturating_sub(message.chars().count() + header.chars().count());
+        match self.output_text(room) {
+            Some(output) if room > 0 => format!("{}{}{}", message, header, output),
+            _ => truncate(&message, max),
+        }
+    }
+
+    /// Whether this event reports a target that exited unsuccessfully.
+    pub fn is_failure(&self) -> bool {
+        match &self.kind {
+            EventKind::Finished {
+                timed_out: Some(_), ..
+            } => true,
+            EventKind::Finished {
+                status: Some(status),
+                ..
+            }
+            | EventKind::Exited {
+                status: Some(status),
+                ..
+            } => !status.success(),
+            _ => false,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match &self.kind {
+            EventKind::Started => Severity::Info,
+            EventKind::Finished { .. } if self.is_failure() => Severity::Failure,
+            EventKind::Finished { status: None, .. } => Severity::Info,
+            EventKind::Finished { .. } => Severity::Success,
+            EventKind::Exited { .. } if self.is_failure() => Severity::Failure,
+            EventKind::Exited { status: None, .. } => Severity::Info,
+            EventKind::Exited { .. } => Severity::Success,
+            EventKind::Alert { .. } => Severity::Warning,
+            EventKind::Heartbeat { .. } => Severity::Info,
+            EventKind::Stalled { .. } => Severity::Warning,
+            EventKind::Resumed { .. } => Severity::Info,
+            EventKind::Restarting { .. } => Severity::Warning,
+            EventKind::GaveUp { .. } => Severity::Failure,
+        }
+    }
+
+    /// Labelled details for backends that render fields, e.g. "Exit status".
+    /// The target itself comes first; the host is left to the backend.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![(self.target.label(), self.target.value())];
+        if let Some(pid) = self.pid {
+            if !matches!(self.target, Target::Pid(_)) {
+                fields.push(("PID", pid.to_string()));
+            }
+        }
+        match &self.kind {
+            EventKind::Started => {}
+            EventKind::Finished {
+                status,
+                duration,
+                timed_out,
+            } => {
+                if let Some(limit) = timed_out {
+                    fields.push(("Timed out after", format_duration(*limit)));
+                }
+                let describe = match timed_out {
+                    Some(_) => describe_timeout_status,
+                    None => describe_status,
+                };
+                let status = status
+                    .as_ref()
+                    .map(describe)
+                    .unwrap_or_else(|| "unknown".to_string());
+                fields.push(("Exit status", status));
+                fields.push(("Duration", format_duration(*duration)));
+            }
+            EventKind::Exited { status, duration } => {
+                fields.push(("Exit status", describe_optional_status(status)));
+                if let Some(duration) = duration {
+                    fields.push(("Ran for", format_duration(*duration)));
+                }
+            }
+            EventKind::Alert {
+                pattern,
+                suppressed,
+                ..
+            } => {
+                fields.push(("Pattern", pattern.clone()));
+                if *suppressed > 0 {
+                    fields.push(("Suppressed matches", suppressed.to_string()));
+                }
+            }
+            EventKind::Heartbeat {
+                elapsed,
+                usage,
+                last_line,
+            } => {
+                fields.push(("Running for", format_duration(*elapsed)));
+                if let Some(cpu) = usage.cpu_percent {
+                    fields.push(("CPU", format!("{:.0}%", cpu)));
+                }
+                if let Some(memory) = usage.memory {
+                    fields.push(("Memory", format_bytes(memory)));
+                }
+                if let Some(line) = last_line {
+                    fields.push(("Last output", line.clone()));
+                }
+            }
+            EventKind::Stalled { activity, idle } | EventKind::Resumed { activity, idle } => {
+                let label = match activity {
+                    Activity::Output => "Without output for",
+                    Activity::Cpu => "Without CPU use for",
+                };
+                fields.push((label, format_duration(*idle)));
+            }
+            EventKind::Restarting {
+                status,
+                duration,
+                restart,
+                max_restarts,
+                delay,
+            } => {
+                fields.push(("Exit status", describe_optional_status(status)));
+                fields.push(("Duration", format_duration(*duration)));
+                let restart = match max_restarts {
+                    Some(max) => format!("{} of {}", restart, max),
+                    None => restart.to_string(),
+                };
+                fields.push(("Restart", restart));
+                fields.push(("Restarting in", format_duration(*delay)));
+            }
+            EventKind::GaveUp {
+                status,
+                duration,
+                restarts,
+                reason,
+            } => {
+                fields.push(("Exit status", describe_optional_status(status)));
+                fields.push(("Duration", format_duration(*duration)));
+                fields.push(("Restarts", restarts.to_string()));
+                fields.push(("Reason", reason.describe()));
+            }
+        }
+        fields
+    }
+
+    /// Short one-line heading, e.g. "Command finished".
+    pub fn title(&self) -> String {
+        let subject = match self.target {
+            Target::Pid(_) => "Process",
+            Target::Name(_) => "Processes",
+            Target::Command(_) => "Command",
+        };
+        match self.kind {
+            EventKind::Started => format!("{} started", subject),
+            EventKind::Finished {
+                timed_out: Some(_), ..
+            } => format!("{} timed out", subject),
+            EventKind::Finished { .. } => format!("{} finished", subject),
+            EventKind::Exited { .. } => "Process exited".to_string(),
+            EventKind::Alert { .. } => format!("{} output matched", subject),
+            EventKind::Heartbeat { .. } => format!("{} still running", subject),
+            EventKind::Stalled { .. } => format!("{} stalled", subject),
+            EventKind::Resumed { .. } => format!("{} resumed", subject),
+            EventKind::Restarting { .. } => format!("{} restarting", subject),
+            EventKind::GaveUp { .. } => format!("Gave up on {}", subject.to_lowercase()),
+        }
+    }
+
+    /// Plain-text rendering used by backends without richer formatting.
+    pub fn message(&self) -> String {
+        match (&self.kind, &self.target) {
+            (EventKind::Started, Target::Pid(pid)) => format!("Starting to monitor PID: {}", pid),
+            (EventKind::Started, Target::Name(name)) => {
+                format!("Monitoring processes named: {}", name)
+            }
+            (EventKind::Started, Target::Command(command)) => match self.pid {
+                Some(pid) => format!("Starting command: '{}', PID: {}", command, pid),
+                None => format!("Starting command: '{}'", command),
+            },
+            (
+                EventKind::Finished {
+                    status,
+                    duration,
+                    timed_out,
+                },
+                target,
+            ) => {
+                let what = match target {
+                    Target::Pid(pid) => format!("Process {} has finished", pid),
+                    Target::Name(name) => format!("Processes '{}' have finished", name),
+                    Target::Command(command) => format!("Command '{}' has finished", command),
+                };
+                let mut message = match (timed_out, status) {
+                    (Some(limit), status) => format!(
+                        "{} '{}' timed out after {} and was stopped ({}).",
+                        target.label(),
+                        target.value(),
+                        format_duration(*limit),
+                        status
+                            .as_ref()
+                            .map(describe_timeout_status)
+                            .unwrap_or_else(|| "unknown status".to_string())
+                    ),
+                    (None, Some(status)) => format!(
+                        "{} ({}) after {}.",
+                        what,
+                        describe_status(status),
+                        format_duration(*duration)
+                    ),
+                    (None, None) => format!("{} after {}.", what, format_duration(*duration)),
+                };
+                if !self.log_files.is_empty() {
+                    let paths: Vec<_> = self
+                        .log_files
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect();
+                    message.push_str(&format!(" Full output: {}", paths.join(", ")));
+                }
+                message
+            }
+            (EventKind::Exited { status, duration }, target) => {
+                let pid = self.pid.map(|pid| format!(" {}", pid)).unwrap_or_default();
+                let mut message = format!(
+                    "Process{} matching {} '{}' exited",
+                    pid,
+                    target.label().to_lowercase(),
+                    target.value()
+                );
+                if let Some(status) = status {
+                    message.push_str(&format!(" ({})", describe_status(status)));
+                }
+                if let Some(duration) = duration {
+                    message.push_str(&format!(
+                        " after running for {}",
+                        format_duration(*duration)
+                    ));
+                }
+                message.push('.');
+                message
+            }
+            (
+                EventKind::Alert {
+                    pattern,
+                    line,
+                    suppressed,
+                },
+                target,
+            ) => {
+                let mut message = format!(
+                    "{} '{}' printed a line matching '{}': {}",
+                    target.label(),
+                    target.value(),
+                    pattern,
+                    line
+                );
+                if *suppressed > 0 {
+                    message.push_str(&format!(
+                        " ({} more matches since the previous alert)",
+                        suppressed
+                    ));
+                }
+                message
+            }
+            (
+                EventKind::Heartbeat {
+                    elapsed,
+                    usage,
+                    last_line,
+                },
+                target,
+            ) => {
+                let mut message = format!(
+                    "{} '{}' is still running after {}",
+                    target.label(),
+                    target.value(),
+                    format_duration(*elapsed)
+                );
+                let mut usage_parts = Vec::new();
+                if let Some(cpu) = usage.cpu_percent {
+                    usage_parts.push(format!("CPU {:.0}%", cpu));
+                }
+                if let Some(memory) = usage.memory {
+                    usage_parts.push(format!("memory {}", format_bytes(memory)));
+                }
+                if !usage_parts.is_empty() {
+                    message.push_str(&format!(" ({})", usage_parts.join(", ")));
+                }
+                message.push('.');
+                if let Some(line) = last_line {
+                    message.push_str(&format!(" Last output: {}", line));
+                }
+                message
+            }
+            (EventKind::Stalled { activity, idle }, target) => {
+                let what = match activity {
+                    Activity::Output => "has printed nothing",
+                    Activity::Cpu => "has used no CPU time",
+                };
+                format!(
+                    "{} '{}' {} for {}, it may be stuck.",
+                    target.label(),
+                    target.value(),
+                    what,
+                    format_duration(*idle)
+                )
+            }
+            (EventKind::Resumed { activity, idle }, target) => {
+                let what = match activity {
+                    Activity::Output => "is printing output again",
+                    Activity::Cpu => "is using CPU again",
+                };
+                format!(
+                    "{} '{}' {} after {} of inactivity.",
+                    target.label(),
+                    target.value(),
+                    what,
+                    format_duration(*idle)
+                )
+            }
+            (
+                EventKind::Restarting {
+                    status,
+                    duration,
+                    restart,
+                    max_restarts,
+                    delay,
+                },
+                target,
+            ) => {
+                let restart = match max_restarts {
+                    Some(max) => format!("restart {} of {}", restart, max),
+                    None => format!("restart {}", restart),
+                };
+                format!(
+                    "{} '{}' exited ({}) after {}, restarting in {} ({}).",
+                    target.label(),
+                    target.value(),
+                    describe_optional_status(status),
+                    format_duration(*duration),
+                    format_duration(*delay),
+                    restart
+                )
+            }
+            (
+                EventKind::GaveUp {
+                    status,
+                    duration,
+                    restarts,
+                    reason,
+                },
+                target,
+            ) => format!(
+                "{} '{}' exited ({}) after {}. Giving up after {} restarts: {}.",
+                target.label(),
+                target.value(),
+                describe_optional_status(status),
+                format_duration(*duration),
+                restarts,
+                reason.describe()
+            ),
+        }
+    }
+}
+
+/// How far a running command has come, as parsed from its output.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+    /// Completion in percent, 0 to 100.
+    pub percent: Option<f64>,
+    pub current: Option<f64>,
+    pub total: Option<f64>,
+    /// Throughput as printed by the command or estimated, e.g. "9.87it/s".
+    pub rate: Option<String>,
+    /// Estimated time left as printed by the command or estimated.
+    pub eta: Option<String>,
+    /// Time since monitoring started.
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Width of the bar drawn by [`Progress::bar`].
+    const BAR_WIDTH: usize = 20;
+
+    /// A bar of block characters, e.g. "██████░░░░░░░░░░░░░░".
+    pub fn bar(&self) -> Option<String> {
+        let percent = self.percent?.clamp(0.0, 100.0);
+        let filled = (percent / 100.0 * Self::BAR_WIDTH as f64).round() as usize;
+        Some("█".repeat(filled) + &"░".repeat(Self::BAR_WIDTH - filled))
+    }
+
+    /// One-line summary, e.g. "42% · 21/50 · 1.50/s · ETA 19s".
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(percent) = self.percent {
+            parts.push(format!("{:.0}%", percent));
+        }
+        if let (Some(current), Some(total)) = (self.current, self.total) {
+            parts.push(format!("{}/{}", format_count(current), format_count(total)));
+        } else if let Some(current) = self.current {
+            parts.push(format_count(current));
+        }
+        if let Some(rate) = &self.rate {
+            parts.push(rate.clone());
+        }
+        if let Some(eta) = &self.eta {
+            parts.push(format!("ETA {}", eta));
+        }
+        parts.join(" · ")
+    }
+
+    /// Plain-text status for `target`, e.g. for a message that is edited in place.
+    pub fn message(&self, target: &Target) -> String {
+        let mut message = format!("{} '{}' is running.", target.label(), target.value());
+        if let Some(bar) = self.bar() {
+            message.push_str(&format!("\n{}", bar));
+        }
+        message.push_str(&format!("\n{}", self.summary()));
+        message.push_str(&format!("\nElapsed: {}", format_duration(self.elapsed)));
+        message
+    }
+}
+
+/// Formats a counter without a pointless fraction, e.g. "3" but "2.5".
+fn format_count(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// [`describe_status`] for statuses that may be unknown.
+fn describe_optional_status(status: &Option<ExitStatus>) -> String {
+    status
+        .as_ref()
+        .map(describe_status)
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Like [`describe_status`], for a target argus stopped itself. The signal
+/// came from argus, so there is no point guessing who else sent it.
+pub fn describe_timeout_status(status: &ExitStatus) -> String {
+    match status.signal() {
+        Some(signal) => format!("killed by {}", signal_name(signal)),
+        None => describe_status(status),
+    }
+}
+
+/// Human-readable exit status, e.g. "success", "exit code 2" or
+/// "killed by SIGKILL (likely out of memory)".
+pub fn describe_status(status: &ExitStatus) -> String {
+    if let Some(code) = status.code() {
+        return match code {
+            0 => "success".to_string(),
+            code => format!("exit code {}", code),
+        };
+    }
+    let Some(signal) = status.signal() else {
+        return "unknown".to_string();
+    };
+    let mut description = format!("killed by {}", signal_name(signal));
+    if signal == libc::SIGKILL {
+        // Nobody sends SIGKILL by hand very often, the kernel OOM killer does.
+        description.push_str(" (likely out of memory)");
+    }
+    if status.core_dumped() {
+        description.push_str(", core dumped");
+    }
+    description
+}
+
+/// Conventional name of a signal, e.g. "SIGTERM", or "signal 42" for unnamed ones.
+pub fn signal_name(signal: i32) -> String {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGCHLD => "SIGCHLD",
+        libc::SIGCONT => "SIGCONT",
+        libc::SIGSTOP => "SIGSTOP",
+        libc::SIGTSTP => "SIGTSTP",
+        libc::SIGTTIN => "SIGTTIN",
+        libc::SIGTTOU => "SIGTTOU",
+        libc::SIGURG => "SIGURG",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        libc::SIGVTALRM => "SIGVTALRM",
+        libc::SIGPROF => "SIGPROF",
+        libc::SIGWINCH => "SIGWINCH",
+        libc::SIGIO => "SIGIO",
+        libc::SIGSYS => "SIGSYS",
+        _ => return format!("signal {}", signal),
+    };
+    name.to_string()
+}
+
+/// The exit code a shell would report for `status`: the child's own code,
+/// or 128 + the signal number if it was killed.
+pub fn exit_code(status: &ExitStatus) -> i32 {
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1)
+}
+
+/// Formats a byte count with a binary unit, e.g. "512 B" or "1.5 GiB".
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Formats a duration with second precision, e.g. "1h 2m 3s".
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Name of the machine argus runs on, resolved once.
+pub fn hostname() -> &'static str {
+    static HOSTNAME: OnceLock<String> = OnceLock::new();
+    HOSTNAME.get_or_init(|| {
+        let mut buf = [0u8; 256];
+        // SAFETY: the buffer is valid for `buf.len()` bytes and gethostname
+        // NUL-terminates on success; the last byte stays 0 if it truncates.
+        let rc = unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len() - 1) };
+        if rc != 0 {
+            return "unknown".to_string();
+        }
+        CStr::from_bytes_until_nul(&buf)
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| "unknown".to_string())
+    })
+}
+
+/// Escapes text for inclusion in an HTML document.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Shortens `text` to at most `max` characters, marking the cut with an ellipsis.
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(max.saturating_sub(1)).collect();
+    short.push('…');
+    short
+}
+
+/// Sends the request built by `request` up to `max_attempts` times. Server
+/// errors and failed connections are retried with exponential backoff, rate
+/// limits after the delay `retry_after` reads from the `429` response, if any.
+/// URLs are kept out of errors, many services embed secrets in them.
+async fn send_with_retries<F>(
+    max_attempts: u32,
+    request: impl Fn() -> RequestBuilder,
+    retry_after: impl Fn(Response) -> F,
+) -> Result<(), NotifyError>
+where
+    F: Future<Output = Option<Duration>>,
+{
+    let mut backoff = Duration::from_secs(1);
+    for attempt in 1..=max_attempts {
+        let delay = match request().send().await {
+            Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                retry_after(response)
+                    .await
+                    .unwrap_or(backoff)
+                    .min(MAX_RETRY_DELAY)
+            }
+            Ok(response) if response.status().is_server_error() => backoff,
+            Ok(response) => {
+                response.error_for_status().map_err(|e| e.without_url())?;
+                return Ok(());
+            }
+            Err(e) if attempt == max_attempts => return Err(e.without_url().into()),
+            Err(_) => backoff,
+        };
+        if attempt < max_attempts {
+            sleep(delay).await;
+            backoff *= 2;
+        }
+    }
+    Err(format!("giving up after {} attempts", max_attempts).into())
+}
+
+/// Renders an error followed by its sources, e.g. "error sending request: connection refused".
+fn error_chain(error: &(dyn std::error::Error + 'static)) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+/// A channel that lifecycle events can be delivered to.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Short human-readable name used in error messages.
+    fn name(&self) -> &str;
+
+    /// Checks the configuration against the remote service before monitoring
+    /// starts, so mistakes surface immediately rather than at the first event.
+    async fn validate(&self) -> Result<(), NotifyError> {
+        Ok(())
+    }
+
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError>;
+
+    /// Whether this notifier uses [`Event::output_file`], which is only
+    /// written if some notifier asks for it.
+    fn wants_output_file(&self) -> bool {
+        false
+    }
+
+    /// Reports progress of a running `target`. Backends that cannot update a
+    /// message in place ignore it rather than sending a message per update.
+    async fn progress(&self, _target: &Target, _progress: &Progress) -> Result<(), NotifyError> {
+        Ok(())
+    }
+}
+
+/// The set of configured notifiers. Every event is delivered to all of them.
+#[derive(Default)]
+pub struct NotifierRegistry {
+    notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl NotifierRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, notifier: impl Notifier + 'static) {
+        self.notifiers.push(Box::new(notifier));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notifiers.is_empty()
+    }
+
+    /// Whether any notifier uses the complete output of a run.
+    pub fn wants_output_file(&self) -> bool {
+        self.notifiers.iter().any(|n| n.wants_output_file())
+    }
+
+    /// Validates every notifier concurrently, returning the first failure.
+    pub async fn validate(&self) -> Result<(), NotifyError> {
+        let results = join_all(self.notifiers.iter().map(|n| n.validate())).await;
+        for (notifier, result) in self.notifiers.iter().zip(results) {
+            result.map_err(|e| format!("{}: {}", notifier.name(), error_chain(&*e)))?;
+        }
+        Ok(())
+    }
+
+    /// Deliver `event` to every notifier concurrently. Failures are reported
+    /// on stderr and never abort monitoring.
+    pub async fn notify(&self, event: &Event) {
+        let results = join_all(self.notifiers.iter().map(|n| n.notify(event))).await;
+        for (notifier, result) in self.notifiers.iter().zip(results) {
+            if let Err(e) = result {
+                eprintln!(
+                    "Failed to send {} notification: {}",
+                    notifier.name(),
+                    error_chain(&*e)
+                );
+            }
+        }
+    }
+
+    /// Deliver a progress update to every notifier concurrently. Failures are
+    /// reported like those of [`NotifierRegistry::notify`].
+    pub async fn progress(&self, target: &Target, progress: &Progress) {
+        let results = join_all(self.notifiers.iter().map(|n| n.progress(target, progress))).await;
+        for (notifier, result) in self.notifiers.iter().zip(results) {
+            if let Err(e) = result {
+                eprintln!(
+                    "Failed to send {} progress update: {}",
+                    notifier.name(),
+                    error_chain(&*e)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use reqwest::Client;
+
+use super::{Event, Notifier, NotifyError, Severity};
+
+/// ntfy turns longer messages into attachments.
+const MESSAGE_LIMIT: usize = 4096;
+
+/// Publishes events to an ntfy topic.
+pub struct NtfyNotifier {
+    client: Client,
+    topic_url: String,
+    token: Option<String>,
+    priority: u8,
+    failure_priority: u8,
+    tags: Vec<String>,
+    click: Option<String>,
+}
+
+impl NtfyNotifier {
+    pub fn new(
+        topic_url: impl Into<String>,
+        token: Option<String>,
+        priority: u8,
+        failure_priority: u8,
+        tags: Vec<String>,
+        click: Option<String>,
+    ) -> Self {
+        Self {
+            client: Client::new(),
+            topic_url: topic_url.into(),
+            token,
+            priority,
+            failure_priority,
+            tags,
+            click,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for NtfyNotifier {
+    fn name(&self) -> &str {
+        "ntfy"
+    }
+
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        let mut tags = self.tags.clone();
+        let priority = match event.severity() {
+            Severity::Info => self.priority,
+            Severity::Success => {
+                tags.push("white_check_mark".into());
+                self.priority
+            }
+            Severity::Warning => {
+                tags.push("warning".into());
+                self.failure_priority
+            }
+            Severity::Failure => {
+                tags.push("rotating_light".into());
+                self.failure_priority
+            }
+        };
+
+        let mut request = self
+            .client
+            .post(&self.topic_url)
+            .header("Title", event.title())
+            .header("Priority", priority.to_string())
+            .body(event.message_with_output(MESSAGE_LIMIT));
+        if !tags.is_empty() {
+            request = request.header("Tags", tags.join(","));
+        }
+        if let Some(click) = &self.click {
+            request = request.header("Click", click);
+        }
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            // Anyone who knows a topic URL can publish to it, keep it out of error messages.
+            .map_err(|e| e.without_url())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use super::{truncate, Event, Notifier, NotifyError, Target};
+
+/// Slack rejects section texts longer than this.
+const SECTION_TEXT_LIMIT: usize = 3000;
+/// Slack rejects section fields longer than this.
+const FIELD_TEXT_LIMIT: usize = 2000;
+
+/// Posts events to a Slack incoming webhook as Block Kit messages.
+pub struct SlackNotifier {
+    client: Client,
+    webhook_url: String,
+}
+
+impl SlackNotifier {
+    pub fn new(webhook_url: impl Into<String>) -> Self {
+        Self {
+            client: Client::new(),
+            webhook_url: webhook_url.into(),
+        }
+    }
+
+    fn payload(event: &Event) -> Value {
+        let fields: Vec<Value> = event
+            .fields()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (label, value))| {
+                // Leave room for the label and for escaping.
+                (i, label, truncate(&value, FIELD_TEXT_LIMIT / 2))
+            })
+            .map(|(i, label, value)| match (i, &event.target) {
+                // The target itself, except a bare PID, reads best as code.
+                (0, Target::Command(_) | Target::Name(_)) => {
+                    field(label, &format!("`{}`", escape(&value)))
+                }
+                _ => field(label, &escape(&value)),
+            })
+            .collect();
+
+        let mut blocks = vec![
+            json!({
+                "type": "header",
+                "text": { "type": "plain_text", "text": event.title() },
+            }),
+            json!({ "type": "section", "fields": fields }),
+        ];
+        // Leave room for the code fence and for escaping.
+        if let Some(output) = event.output_text(SECTION_TEXT_LIMIT / 2) {
+            blocks.push(json!({
+                "type": "section",
+                "text": {
+                    "type": "mrkdwn",
+                    "text": format!("```{}```", escape(&output)),
+                },
+            }));
+        }
+
+        json!({
+            // Fallback for clients that cannot render blocks, e.g. notifications.
+            "text": event.message(),
+            "blocks": blocks,
+        })
+    }
+}
+
+fn field(label: &str, value: &str) -> Value {
+    let text = format!("*{}*\n{}", label, value);
+    json!({ "type": "mrkdwn", "text": truncate(&text, FIELD_TEXT_LIMIT) })
+}
+
+/// Escapes the characters Slack treats as control sequences in mrkdwn.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &str {
+        "Slack"
+    }
+
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        self.client
+            .post(&self.webhook_url)
+            .json(&Self::payload(event))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            // Webhook URLs are secrets, keep them out of error messages.
+            .map_err(|e| e.without_url())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::testing::serve_once;
+    use std::{os::unix::process::ExitStatusExt, process::ExitStatus, time::Duration};
+    use tokio::net::TcpListener;
+
+    async fn local_notifier() -> (SlackNotifier, TcpListener) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks/secret", listener.local_addr().unwrap());
+        (SlackNotifier::new(url), listener)
+    }
+
+    #[tokio::test]
+    async fn posts_block_kit_message() {
+        let (notifier, listener) = local_notifier().await;
+        let command = format!("make <all> {}", "x".repeat(5000));
+        let event = Event::finished(
+            Target::Command(command),
+            Some(42),
+            Some(ExitStatus::from_raw(2 << 8)),
+            Duration::from_secs(3),
+        )
+        .with_output(vec!["a & b".into()]);
+
+        let (result, body) = tokio::join!(
+            notifier.notify(&event),
+            serve_once(&listener, "200 OK", &[])
+        );
+        result.unwrap();
+
+        let payload: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["text"], event.message());
+        let blocks = payload["blocks"].as_array().unwrap();
+        assert_eq!(blocks[0]["type"], "header");
+        let fields = blocks[1]["fields"].as_array().unwrap();
+        let target = fields[0]["text"].as_str().unwrap();
+        assert!(target.starts_with("*Command*\n`make &lt;all&gt; xxx"));
+        for field in fields {
+            assert!(field["text"].as_str().unwrap().chars().count() <= FIELD_TEXT_LIMIT);
+        }
+        assert_eq!(blocks[2]["text"]["text"], "```a &amp; b```");
+    }
+
+    #[tokio::test]
+    async fn reports_rejected_messages_without_the_url() {
+        let (notifier, listener) = local_notifier().await;
+        let event = Event::started(Target::Pid(42), Some(42));
+
+        let (result, _) = tokio::join!(
+            notifier.notify(&event),
+            serve_once(&listener, "400 Bad Request", &[])
+        );
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("400"), "{}", error);
+        assert!(!error.contains("secret"), "{}", error);
+    }
+}
@@ -0,0 +1,253 @@
+use async_trait::async_trait;
+use flate2::{write::GzEncoder, Compression};
+use reqwest::{
+    multipart::{Form, Part},
+    Client, RequestBuilder,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{io::Write, sync::Mutex};
+use tokio::fs;
+
+use super::{truncate, Event, EventKind, Notifier, NotifyError, Progress, Target};
+
+pub const DEFAULT_API_URL: &str = "https://api.telegram.org";
+
+/// Telegram rejects longer messages.
+const MESSAGE_LIMIT: usize = 4096;
+
+/// Whether and how output that does not fit into a message is uploaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputUpload {
+    Off,
+    /// As `output.log`.
+    #[default]
+    Plain,
+    /// As `output.log.gz`.
+    Gzip,
+}
+
+/// Sends events as messages from a Telegram bot to a single chat.
+pub struct TelegramNotifier {
+    client: Client,
+    api_url: String,
+    bot_token: String,
+    chat_id: String,
+    upload: OutputUpload,
+    /// ID of the last start message, which later messages reply to.
+    start_message: Mutex<Option<i64>>,
+    /// ID of the status message that progress updates edit in place.
+    progress_message: Mutex<Option<i64>>,
+}
+
+impl TelegramNotifier {
+    pub fn new(
+        api_url: impl Into<String>,
+        bot_token: impl Into<String>,
+        chat_id: impl Into<String>,
+        upload: OutputUpload,
+    ) -> Self {
+        Self {
+            client: Client::new(),
+            api_url: api_url.into(),
+            bot_token: bot_token.into(),
+            chat_id: chat_id.into(),
+            upload,
+            start_message: Mutex::new(None),
+            progress_message: Mutex::new(None),
+        }
+    }
+
+    fn method_url(&self, method: &str) -> String {
+        format!(
+            "{}/bot{}/{}",
+            self.api_url.trim_end_matches('/'),
+            self.bot_token,
+            method
+        )
+    }
+
+    /// Calls a Bot API method and returns its `result`, turning `"ok": false`
+    /// replies into errors carrying Telegram's description.
+    async fn call(&self, method: &str, params: &[(&str, &str)]) -> Result<Value, NotifyError> {
+        let request = self.client.post(self.method_url(method)).form(params);
+        self.send(method, request).await
+    }
+
+    async fn send(&self, method: &str, request: RequestBuilder) -> Result<Value, NotifyError> {
+        let response = request
+            .send()
+            .await
+            // The request URL embeds the bot token, keep it out of error messages.
+            .map_err(|e| e.without_url())?;
+        let status = response.status();
+        let mut body: Value = response.json().await.map_err(|e| e.without_url())?;
+        if body["ok"].as_bool() != Some(true) {
+            let description = body["description"].as_str().unwrap_or("no description");
+            return Err(format!("{} failed ({}): {}", method, status, description).into());
+        }
+        Ok(body["result"].take())
+    }
+
+    /// `reply_parameters` pointing at the start message, if there is one.
+    fn reply_parameters(&self) -> Option<String> {
+        let message_id = (*self.start_message.lock().unwrap())?;
+        Some(json!({ "message_id": message_id, "allow_sending_without_reply": true }).to_string())
+    }
+
+    /// Sends a text message and returns its ID.
+    async fn send_message(&self, message: &str) -> Result<Option<i64>, NotifyError> {
+        let reply = self.reply_parameters();
+        let mut params = vec![("chat_id", self.chat_id.as_str()), ("text", message)];
+        if let Some(reply) = &reply {
+            params.push(("reply_parameters", reply));
+        }
+        let result = self.call("sendMessage", &params).await?;
+        Ok(result["message_id"].as_i64())
+    }
+
+    /// Replaces the text of a message sent earlier.
+    async fn edit_message(&self, message_id: i64, message: &str) -> Result<(), NotifyError> {
+        let message_id = message_id.to_string();
+        let params = [
+            ("chat_id", self.chat_id.as_str()),
+            ("message_id", &message_id),
+            ("text", message),
+        ];
+        match self.call("editMessageText", &params).await {
+            // Telegram refuses edits that leave the text as it was.
+            Err(e) if e.to_string().contains("message is not modified") => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn send_document(&self, file_name: &str, contents: Vec<u8>) -> Result<(), NotifyError> {
+        let mut form = Form::new().text("chat_id", self.chat_id.clone()).part(
+            "document",
+            Part::bytes(contents).file_name(file_name.to_string()),
+        );
+        if let Some(reply) = self.reply_parameters() {
+            form = form.text("reply_parameters", reply);
+        }
+        let request = self
+            .client
+            .post(self.method_url("sendDocument"))
+            .multipart(form);
+        self.send("sendDocument", request).await?;
+        Ok(())
+    }
+
+    /// The message for `event` with its complete output instead of only the
+    /// tail, if the spooled output fits into one message.
+    async fn message_with_full_output(&self, event: &Event) -> Result<Option<String>, NotifyError> {
+        let Some(path) = &event.output_file else {
+            return Ok(None);
+        };
+        // A character takes at most 4 bytes, larger files cannot fit.
+        if fs::metadata(path).await?.len() > 4 * MESSAGE_LIMIT as u64 {
+            return Ok(None);
+        }
+        let text = String::from_utf8_lossy(&fs::read(path).await?).into_owned();
+        let full = event
+            .clone()
+            .with_output(text.lines().map(str::to_string).collect());
+        let message = full.message_with_output(MESSAGE_LIMIT);
+        Ok(message
+            .ends_with(&full.output.join("\n"))
+            .then_some(message))
+    }
+
+    /// The complete output of a finished run, if it does not fit into the message.
+    async fn full_output(&self, event: &Event) -> Result<Option<Vec<u8>>, NotifyError> {
+        if let Some(path) = &event.output_file {
+            return Ok(Some(fs::read(path).await?));
+        }
+        // The tail is all there is; upload it only if the message had to cut it.
+        let output = event.output.join("\n");
+        if event.message_with_output(MESSAGE_LIMIT).ends_with(&output) {
+            return Ok(None);
+        }
+        Ok(Some(output.into_bytes()))
+    }
+
+    async fn upload_output(&self, event: &Event) -> Result<(), NotifyError> {
+        if self.upload == OutputUpload::Off {
+            return Ok(());
+        }
+        let Some(output) = self.full_output(event).await? else {
+            return Ok(());
+        };
+        match self.upload {
+            OutputUpload::Off => Ok(()),
+            OutputUpload::Plain => self.send_document("output.log", output).await,
+            OutputUpload::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&output)?;
+                self.send_document("output.log.gz", encoder.finish()?).await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &str {
+        "Telegram"
+    }
+
+    async fn validate(&self) -> Result<(), NotifyError> {
+        self.call("getMe", &[]).await?;
+        Ok(())
+    }
+
+    fn wants_output_file(&self) -> bool {
+        self.upload != OutputUpload::Off
+    }
+
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        let mut message = event.message_with_output(MESSAGE_LIMIT);
+        let mut upload = matches!(
+            event.kind,
+            EventKind::Finished { .. } | EventKind::GaveUp { .. }
+        ) && !event.output.is_empty();
+        if upload {
+            if let Some(full) = self.message_with_full_output(event).await? {
+                message = full;
+                upload = false;
+            }
+        }
+        let message_id = self.send_message(&message).await?;
+        match event.kind {
+            EventKind::Started => {
+                *self.start_message.lock().unwrap() = message_id;
+                *self.progress_message.lock().unwrap() = None;
+            }
+            EventKind::Finished { .. } | EventKind::GaveUp { .. } if upload => {
+                self.upload_output(event).await?
+            }
+            EventKind::Finished { .. }
+            | EventKind::Exited { .. }
+            | EventKind::GaveUp { .. }
+            | EventKind::Restarting { .. }
+            | EventKind::Alert { .. }
+            | EventKind::Heartbeat { .. }
+            | EventKind::Stalled { .. }
+            | EventKind::Resumed { .. } => {}
+        }
+        Ok(())
+    }
+
+    async fn progress(&self, target: &Target, progress: &Progress) -> Result<(), NotifyError> {
+        let message = truncate(&progress.message(target), MESSAGE_LIMIT);
+        let existing = *self.progress_message.lock().unwrap();
+        match existing {
+            Some(message_id) => self.edit_message(message_id, &message).await,
+            None => {
+                let message_id = self.send_message(&message).await?;
+                *self.progress_message.lock().unwrap() = message_id;
+                Ok(())
+            }
+        }
+    }
+}
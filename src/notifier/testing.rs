@@ -0,0 +1,42 @@
+//! Stand-ins for remote services, shared by the backends' tests.
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// Accepts one HTTP request, answers it with `status` and `headers` and
+/// returns the request body.
+pub async fn serve_once(listener: &TcpListener, status: &str, headers: &[(&str, &str)]) -> Vec<u8> {
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let mut request = Vec::new();
+    let mut buffer = [0; 4096];
+    let body_start = loop {
+        let read = stream.read(&mut buffer).await.unwrap();
+        assert!(read > 0, "connection closed before the headers ended");
+        request.extend_from_slice(&buffer[..read]);
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+    };
+    let request_headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+    let length: usize = request_headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .expect("request has a content length")
+        .trim()
+        .parse()
+        .unwrap();
+    while request.len() < body_start + length {
+        let read = stream.read(&mut buffer).await.unwrap();
+        assert!(read > 0, "connection closed before the body ended");
+        request.extend_from_slice(&buffer[..read]);
+    }
+    let mut response = format!("HTTP/1.1 {}\r\n", status);
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("Content-Length: 0\r\nConnection: close\r\n\r\n");
+    stream.write_all(response.as_bytes()).await.unwrap();
+    request.split_off(body_start)
+}
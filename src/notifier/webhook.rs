@@ -0,0 +1,298 @@
+use async_trait::async_trait;
+use hmac::{Hmac, KeyInit, Mac};
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde::Serialize;
+use sha2::Sha256;
+use std::os::unix::process::ExitStatusExt;
+
+use super::{hostname, signal_name, Event, EventKind, Notifier, NotifyError, Target};
+
+/// Version of the JSON document, bumped on incompatible changes.
+const SCHEMA_VERSION: u32 = 1;
+
+/// POSTs events as JSON documents to an arbitrary URL, optionally signed with
+/// HMAC-SHA256 so the receiver can authenticate them.
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+    secret: Option<String>,
+}
+
+/// The wire format of an event. Fields are only ever added, never renamed.
+#[derive(Serialize)]
+struct Document<'a> {
+    version: u32,
+    event: &'static str,
+    target: &'static str,
+    command: Option<&'a str>,
+    process_name: Option<&'a str>,
+    pid: Option<u32>,
+    exit_code: Option<i32>,
+    signal: Option<i32>,
+    signal_name: Option<String>,
+    duration_secs: Option<f64>,
+    /// The time limit, if the target was stopped for exceeding it.
+    timed_out_after_secs: Option<f64>,
+    hostname: &'a str,
+    timestamp: String,
+    started_at: Option<String>,
+    output: &'a [String],
+    log_files: Vec<String>,
+    alert: Option<Alert<'a>>,
+    heartbeat: Option<Heartbeat<'a>>,
+    stall: Option<Stall>,
+    restart: Option<Restart>,
+}
+
+#[derive(Serialize)]
+struct Alert<'a> {
+    pattern: &'a str,
+    line: &'a str,
+    suppressed: usize,
+}
+
+#[derive(Serialize)]
+struct Heartbeat<'a> {
+    cpu_percent: Option<f64>,
+    memory_bytes: Option<u64>,
+    last_line: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Stall {
+    activity: &'static str,
+    idle_secs: f64,
+}
+
+#[derive(Serialize)]
+struct Restart {
+    /// Restarts so far, including the upcoming one.
+    restarts: usize,
+    max_restarts: Option<usize>,
+    delay_secs: Option<f64>,
+    gave_up_reason: Option<String>,
+}
+
+impl<'a> Document<'a> {
+    fn new(event: &'a Event) -> Self {
+        let mut heartbeat = None;
+        let mut stall = None;
+        let mut timed_out_after = None;
+        let mut restart = None;
+        let (event_type, status, duration, alert) = match &event.kind {
+            EventKind::Started => ("started", None, None, None),
+            EventKind::Finished {
+                status,
+                duration,
+                timed_out,
+            } => {
+                timed_out_after = *timed_out;
+                ("finished", *status, Some(*duration), None)
+            }
+            EventKind::Exited { status, duration } => ("exited", *status, *duration, None),
+            EventKind::Heartbeat {
+                elapsed,
+                usage,
+                last_line,
+            } => {
+                heartbeat = Some(Heartbeat {
+                    cpu_percent: usage.cpu_percent,
+                    memory_bytes: usage.memory,
+                    last_line: last_line.as_deref(),
+                });
+                ("heartbeat", None, Some(*elapsed), None)
+            }
+            EventKind::Stalled { activity, idle } | EventKind::Resumed { activity, idle } => {
+                stall = Some(Stall {
+                    activity: activity.name(),
+                    idle_secs: idle.as_secs_f64(),
+                });
+                let event_type = match event.kind {
+                    EventKind::Stalled { .. } => "stalled",
+                    _ => "resumed",
+                };
+                (event_type, None, None, None)
+            }
+            EventKind::Restarting {
+                status,
+                duration,
+                restart: restarts,
+                max_restarts,
+                delay,
+            } => {
+                restart = Some(Restart {
+                    restarts: *restarts,
+                    max_restarts: *max_restarts,
+                    delay_secs: Some(delay.as_secs_f64()),
+                    gave_up_reason: None,
+                });
+                ("restarting", *status, Some(*duration), None)
+            }
+            EventKind::GaveUp {
+                status,
+                duration,
+                restarts,
+                reason,
+            } => {
+                restart = Some(Restart {
+                    restarts: *restarts,
+                    max_restarts: None,
+                    delay_secs: None,
+                    gave_up_reason: Some(reason.describe()),
+                });
+                ("gave_up", *status, Some(*duration), None)
+            }
+            EventKind::Alert {
+                pattern,
+                line,
+                suppressed,
+            } => (
+                "alert",
+                None,
+                None,
+                Some(Alert {
+                    pattern,
+                    line,
+                    suppressed: *suppressed,
+                }),
+            ),
+        };
+        let (target, command, process_name) = match &event.target {
+            Target::Pid(_) => ("pid", None, None),
+            Target::Name(name) => ("name", None, Some(name.as_str())),
+            Target::Command(command) => ("command", Some(command.as_str()), None),
+        };
+        // Unknown for events that say nothing about how long the target ran.
+        let started_at = match (&event.kind, duration) {
+            (EventKind::Started, _) => Some(event.timestamp),
+            (_, Some(duration)) => event.timestamp.checked_sub(duration),
+            (_, None) => None,
+        };
+        Self {
+            version: SCHEMA_VERSION,
+            event: event_type,
+            target,
+            command,
+            process_name,
+            pid: event.pid,
+            exit_code: status.and_then(|status| status.code()),
+            signal: status.and_then(|status| status.signal()),
+            signal_name: status.and_then(|status| status.signal()).map(signal_name),
+            duration_secs: duration.map(|duration| duration.as_secs_f64()),
+            timed_out_after_secs: timed_out_after.map(|limit| limit.as_secs_f64()),
+            hostname: hostname(),
+            timestamp: humantime::format_rfc3339_millis(event.timestamp).to_string(),
+            started_at: started_at.map(|time| humantime::format_rfc3339_millis(time).to_string()),
+            output: &event.output,
+            log_files: event
+                .log_files
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect(),
+            alert,
+            heartbeat,
+            stall,
+            restart,
+        }
+    }
+}
+
+impl WebhookNotifier {
+    pub fn new(url: impl Into<String>, secret: Option<String>) -> Self {
+        Self {
+            client: Client::new(),
+            url: url.into(),
+            secret,
+        }
+    }
+
+    /// `sha256=<hex digest>` of `body` keyed with the shared secret.
+    fn signature(secret: &str, body: &[u8]) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key size");
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn notify(&self, event: &Event) -> Result<(), NotifyError> {
+        let document = Document::new(event);
+        let body = serde_json::to_vec(&document)?;
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header("X-Argus-Event", document.event);
+        if let Some(secret) = &self.secret {
+            request = request.header("X-Argus-Signature", Self::signature(secret, &body));
+        }
+        request
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            WebhookNotifier::signature("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn signature_depends_on_secret_and_body() {
+        let signature = WebhookNotifier::signature("secret", b"{}");
+        assert_ne!(signature, WebhookNotifier::signature("other", b"{}"));
+        assert_ne!(signature, WebhookNotifier::signature("secret", b"{ }"));
+    }
+
+    #[test]
+    fn start_time_is_only_set_when_known() {
+        let started = Event::started(Target::Pid(42), Some(42));
+        let document = Document::new(&started);
+        assert_eq!(document.started_at, Some(document.timestamp.clone()));
+
+        let finished = Event::finished(
+            Target::Pid(42),
+            Some(42),
+            None,
+            std::time::Duration::from_secs(60),
+        );
+        let document = Document::new(&finished);
+        let started_at = humantime::parse_rfc3339(document.started_at.as_deref().unwrap());
+        let timestamp = humantime::parse_rfc3339(&document.timestamp);
+        assert_eq!(
+            timestamp
+                .unwrap()
+                .duration_since(started_at.unwrap())
+                .unwrap(),
+            std::time::Duration::from_secs(60)
+        );
+
+        let exited = Event::exited(Target::Name("worker".into()), 42, None, None);
+        assert_eq!(Document::new(&exited).started_at, None);
+        let alert = Event::alert(
+            Target::Pid(42),
+            Some(42),
+            "error".into(),
+            "error: x".into(),
+            0,
+        );
+        assert_eq!(Document::new(&alert).started_at, None);
+    }
+}
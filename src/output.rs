@@ -0,0 +1,443 @@
+//! Capturing of a spawned command's stdout and stderr.
+//!
+//! Output is copied to the terminal as it arrives and split into lines, the
+//! most recent of which are kept for the finish notification. Every line is
+//! also handed to the registered [`LineSink`]s.
+
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    os::unix::fs::OpenOptionsExt,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    process::Child,
+    task::{self, JoinHandle},
+    time::timeout,
+};
+
+/// Longer lines are cut off so a child that never prints a newline cannot
+/// make the buffer grow without bound.
+const MAX_LINE_BYTES: usize = 4096;
+
+/// How long to keep reading after the child exited. Background processes it
+/// started may hold the pipes open indefinitely.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Splits a byte stream into lines the way a terminal would display them:
+/// `\r` without a following `\n` returns to the start of the line, so
+/// progress bars only leave their final state behind.
+#[derive(Default)]
+struct LineSplitter {
+    line: Vec<u8>,
+    pending_cr: bool,
+}
+
+/// A piece of output produced by [`LineSplitter`].
+#[derive(Debug, PartialEq, Eq)]
+enum Segment {
+    /// A line terminated by `\n`.
+    Line(String),
+    /// Text that was overwritten by returning to the start of the line.
+    Overwritten(String),
+}
+
+impl LineSplitter {
+    fn take_line(&mut self) -> String {
+        String::from_utf8_lossy(&std::mem::take(&mut self.line)).into()
+    }
+
+    fn feed(&mut self, bytes: &[u8], mut emit: impl FnMut(Segment)) {
+        for &byte in bytes {
+            if self.pending_cr && !matches!(byte, b'\r' | b'\n') && !self.line.is_empty() {
+                emit(Segment::Overwritten(self.take_line()));
+            }
+            self.pending_cr = false;
+            match byte {
+                b'\n' => emit(Segment::Line(self.take_line())),
+                b'\r' => self.pending_cr = true,
+                _ if self.line.len() < MAX_LINE_BYTES => self.line.push(byte),
+                _ => {}
+            }
+        }
+    }
+
+    fn finish(mut self, mut emit: impl FnMut(Segment)) {
+        if !self.line.is_empty() {
+            emit(Segment::Line(self.take_line()));
+        }
+    }
+}
+
+/// Which of the child's output streams a line came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+/// Consumer of captured output lines.
+pub trait LineSink: Send {
+    fn line(&mut self, stream: Stream, line: &str);
+
+    /// Text a progress bar or similar printed and then overwrote using `\r`.
+    /// It never reaches [`LineSink::line`].
+    fn overwritten(&mut self, _stream: Stream, _text: &str) {}
+
+    /// Called after each chunk read from the child, once its lines were delivered.
+    fn flush(&mut self) {}
+}
+
+/// Ring buffer holding the last `capacity` lines.
+struct Tail {
+    lines: VecDeque<String>,
+    capacity: usize,
+}
+
+impl Tail {
+    fn push(&mut self, line: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+}
+
+/// Upper bound for the spooled output. Telegram bots cannot upload more than
+/// 50 MB and nobody reads that much log in a chat anyway.
+const MAX_SPOOL_BYTES: u64 = 45 << 20;
+
+/// Keeps the complete output of a run in a file so it can be attached to
+/// notifications. Removing the file afterwards is up to the caller.
+pub struct OutputSpool {
+    file: Option<BufWriter<File>>,
+    written: u64,
+}
+
+impl OutputSpool {
+    /// Creates the spool file, which must not exist yet.
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)?;
+        let file = BufWriter::new(file);
+        Ok(Self {
+            file: Some(file),
+            written: 0,
+        })
+    }
+
+    fn close(&mut self) {
+        if let Some(mut file) = self.file.take() {
+            let _ = file.flush();
+        }
+    }
+}
+
+impl LineSink for OutputSpool {
+    fn line(&mut self, _stream: Stream, line: &str) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        if self.written + line.len() as u64 + 1 > MAX_SPOOL_BYTES {
+            let _ = file.write_all(b"[output truncated]\n");
+            self.close();
+            return;
+        }
+        self.written += line.len() as u64 + 1;
+        let result = file
+            .write_all(line.as_bytes())
+            .and_then(|_| file.write_all(b"\n"));
+        if result.is_err() {
+            // The spool only feeds attachments, losing it is not worth a warning per line.
+            self.file = None;
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(file) = &mut self.file {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// What remains of a run's output once it is over.
+pub struct CapturedOutput {
+    /// The last lines, oldest first.
+    pub tail: Vec<String>,
+    /// Number of lines seen in total, including those no longer in `tail`.
+    pub total_lines: usize,
+}
+
+/// State shared by the stdout and stderr readers.
+struct Shared {
+    tail: Tail,
+    total_lines: usize,
+    last_line: Option<String>,
+    /// When the child last wrote anything, complete line or not.
+    last_activity: Instant,
+    sinks: Vec<Box<dyn LineSink>>,
+}
+
+impl Shared {
+    /// Hands something to every sink. A sink that panics is dropped instead
+    /// of taking the whole capture down with it.
+    fn each_sink(&mut self, mut deliver: impl FnMut(&mut dyn LineSink)) {
+        self.sinks.retain_mut(|sink| {
+            let delivered = panic::catch_unwind(AssertUnwindSafe(|| deliver(sink.as_mut())));
+            if delivered.is_err() {
+                eprintln!("An output consumer failed and was disabled");
+            }
+            delivered.is_ok()
+        });
+    }
+
+    fn segment(&mut self, stream: Stream, segment: Segment) {
+        match segment {
+            Segment::Line(line) => {
+                self.each_sink(|sink| sink.line(stream, &line));
+                self.total_lines += 1;
+                self.last_line
+                    .get_or_insert_with(String::new)
+                    .clone_from(&line);
+                self.tail.push(line);
+            }
+            Segment::Overwritten(text) => {
+                self.each_sink(|sink| sink.overwritten(stream, &text));
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        self.each_sink(|sink| sink.flush());
+    }
+}
+
+/// Tees a child's stdout and stderr to the terminal while keeping its tail.
+pub struct OutputCapture {
+    shared: Arc<Mutex<Shared>>,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl OutputCapture {
+    /// Takes the piped stdout and stderr of `child` and starts reading them.
+    pub fn start(child: &mut Child, tail_lines: usize, sinks: Vec<Box<dyn LineSink>>) -> Self {
+        let shared = Arc::new(Mutex::new(Shared {
+            tail: Tail {
+                lines: VecDeque::with_capacity(tail_lines),
+                capacity: tail_lines,
+            },
+            total_lines: 0,
+            last_line: None,
+            last_activity: Instant::now(),
+            sinks,
+        }));
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(task::spawn(pump(
+                stdout,
+                Stream::Stdout,
+                io::stdout(),
+                shared.clone(),
+            )));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(task::spawn(pump(
+                stderr,
+                Stream::Stderr,
+                io::stderr(),
+                shared.clone(),
+            )));
+        }
+        Self { shared, readers }
+    }
+
+    /// The most recent complete line of output, if any.
+    pub fn last_line(&self) -> Option<String> {
+        self.shared.lock().unwrap().last_line.clone()
+    }
+
+    /// When the child last wrote to stdout or stderr, or started if it never did.
+    pub fn last_activity(&self) -> Instant {
+        self.shared.lock().unwrap().last_activity
+    }
+
+    /// Waits for the streams to close and returns what was captured.
+    pub async fn finish(self) -> CapturedOutput {
+        for mut reader in self.readers {
+            if timeout(DRAIN_TIMEOUT, &mut reader).await.is_err() {
+                reader.abort();
+            }
+        }
+        let mut shared = self.shared.lock().unwrap();
+        shared.flush();
+        // Dropping the sinks closes any files they hold.
+        shared.sinks.clear();
+        CapturedOutput {
+            tail: shared.tail.lines.drain(..).collect(),
+            total_lines: shared.total_lines,
+        }
+    }
+}
+
+/// Copies `reader` to `terminal` verbatim and hands complete lines to `shared`.
+async fn pump(
+    mut reader: impl AsyncRead + Unpin,
+    stream: Stream,
+    mut terminal: impl AsyncWrite + Unpin,
+    shared: Arc<Mutex<Shared>>,
+) {
+    let mut buf = [0u8; 8192];
+    let mut splitter = LineSplitter::default();
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        // A closed terminal must not stop the capture.
+        let _ = terminal.write_all(&buf[..n]).await;
+        let _ = terminal.flush().await;
+        let mut shared = shared.lock().unwrap();
+        shared.last_activity = Instant::now();
+        splitter.feed(&buf[..n], |segment| shared.segment(stream, segment));
+        shared.flush();
+    }
+    let mut shared = shared.lock().unwrap();
+    splitter.finish(|segment| shared.segment(stream, segment));
+    shared.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `chunks` one after another and collects what comes out.
+    fn split(chunks: &[&[u8]]) -> Vec<Segment> {
+        let mut splitter = LineSplitter::default();
+        let mut segments = Vec::new();
+        for chunk in chunks {
+            splitter.feed(chunk, |segment| segments.push(segment));
+        }
+        splitter.finish(|segment| segments.push(segment));
+        segments
+    }
+
+    fn line(text: &str) -> Segment {
+        Segment::Line(text.into())
+    }
+
+    fn overwritten(text: &str) -> Segment {
+        Segment::Overwritten(text.into())
+    }
+
+    #[test]
+    fn splits_lines() {
+        assert_eq!(
+            split(&[b"one\ntwo\n\nthree"]),
+            [line("one"), line("two"), line(""), line("three")]
+        );
+    }
+
+    #[test]
+    fn carriage_return_overwrites_the_line() {
+        assert_eq!(
+            split(&[b" 10%\r 50%\r100%\n"]),
+            [overwritten(" 10%"), overwritten(" 50%"), line("100%")]
+        );
+    }
+
+    #[test]
+    fn crlf_ends_a_line() {
+        assert_eq!(split(&[b"one\r\ntwo\r\n"]), [line("one"), line("two")]);
+        // Split across reads.
+        assert_eq!(split(&[b"one\r", b"\ntwo"]), [line("one"), line("two")]);
+    }
+
+    #[test]
+    fn carriage_return_split_across_reads() {
+        assert_eq!(
+            split(&[b"50%\r", b"100%\n"]),
+            [overwritten("50%"), line("100%")]
+        );
+    }
+
+    #[test]
+    fn repeated_carriage_returns_overwrite_once() {
+        assert_eq!(split(&[b"\r\rdone\r\r\n"]), [line("done")]);
+    }
+
+    #[test]
+    fn trailing_carriage_return_keeps_the_line() {
+        assert_eq!(split(&[b"50%\r"]), [line("50%")]);
+    }
+
+    #[test]
+    fn caps_line_length() {
+        let long = vec![b'x'; MAX_LINE_BYTES + 10];
+        assert_eq!(split(&[&long, b"\n"]), [line(&"x".repeat(MAX_LINE_BYTES))]);
+    }
+
+    /// Counts the lines it sees, panicking on "boom" if `fragile`.
+    struct Counter {
+        lines: Arc<Mutex<usize>>,
+        fragile: bool,
+    }
+
+    impl LineSink for Counter {
+        fn line(&mut self, _stream: Stream, line: &str) {
+            assert!(!(self.fragile && line == "boom"));
+            *self.lines.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    fn panicking_sink_is_dropped() {
+        let fragile = Arc::new(Mutex::new(0));
+        let steady = Arc::new(Mutex::new(0));
+        let mut shared = Shared {
+            tail: Tail {
+                lines: VecDeque::new(),
+                capacity: 10,
+            },
+            total_lines: 0,
+            last_line: None,
+            last_activity: Instant::now(),
+            sinks: vec![
+                Box::new(Counter {
+                    lines: fragile.clone(),
+                    fragile: true,
+                }),
+                Box::new(Counter {
+                    lines: steady.clone(),
+                    fragile: false,
+                }),
+            ],
+        };
+        for text in ["one", "boom", "two"] {
+            shared.segment(Stream::Stdout, line(text));
+        }
+        assert_eq!(shared.total_lines, 3);
+        assert_eq!(shared.sinks.len(), 1);
+        assert_eq!(*fragile.lock().unwrap(), 1);
+        assert_eq!(*steady.lock().unwrap(), 3);
+    }
+}
@@ -0,0 +1,369 @@
+//! Process events from the kernel's netlink process connector.
+//!
+//! The kernel reports every fork, exec and exit as it happens, including the
+//! exit status of processes argus is not the parent of. Kernels before 6.6
+//! require `CAP_NET_ADMIN` to subscribe and none allow it from a user or PID
+//! namespace, so callers must be prepared to fall back to scanning `/proc`.
+
+use std::{
+    io, mem,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::process::ExitStatusExt,
+    },
+    process::ExitStatus,
+    time::{Duration, Instant},
+};
+use tokio::io::{unix::AsyncFd, Interest};
+
+// From <linux/connector.h> and <linux/cn_proc.h>, which libc does not cover.
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+const PROC_CN_MCAST_IGNORE: u32 = 2;
+const PROC_EVENT_NONE: u32 = 0x0000_0000;
+const PROC_EVENT_FORK: u32 = 0x0000_0001;
+const PROC_EVENT_EXEC: u32 = 0x0000_0002;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+const NLMSG_HEADER_LEN: usize = mem::size_of::<libc::nlmsghdr>();
+/// `struct cn_msg` without its payload: id, seq, ack, len and flags.
+const CN_MSG_HEADER_LEN: usize = 20;
+/// How long to wait for the kernel to confirm the subscription.
+const ACK_TIMEOUT_MS: i32 = 1000;
+/// Offset of the event data in `struct proc_event`, after what, cpu and timestamp.
+const EVENT_DATA_OFFSET: usize = 16;
+
+/// A change to the process table. Threads are left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcEvent {
+    Fork {
+        child: u32,
+    },
+    /// The process replaced its program, and with it possibly its name.
+    Exec {
+        pid: u32,
+    },
+    Exit {
+        pid: u32,
+        status: ExitStatus,
+    },
+    /// Events were dropped because they were not read fast enough.
+    Overrun,
+}
+
+/// A subscription to process events.
+pub struct ProcEvents {
+    socket: AsyncFd<OwnedFd>,
+}
+
+impl ProcEvents {
+    /// Subscribes to process events. Fails where the kernel does not allow
+    /// it or was built without the process connector.
+    pub fn subscribe() -> io::Result<Self> {
+        // SAFETY: socket takes no pointers. On success it returns a new file
+        // descriptor nobody else owns.
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+                libc::NETLINK_CONNECTOR,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // SAFETY: sockaddr_nl is plain data, for which all zeroes is valid.
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = CN_IDX_PROC;
+        // SAFETY: the address is a valid sockaddr_nl and its size is passed along.
+        let result = unsafe {
+            libc::bind(
+                socket.as_raw_fd(),
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        control(&socket, PROC_CN_MCAST_LISTEN)?;
+        // Inside a user or PID namespace the kernel refuses the subscription,
+        // which only shows in its acknowledgement.
+        wait_for_ack(&socket)?;
+        let socket = AsyncFd::with_interest(socket, Interest::READABLE)?;
+        Ok(Self { socket })
+    }
+
+    /// Waits for the next batch of events.
+    pub async fn next(&self) -> io::Result<Vec<ProcEvent>> {
+        let mut buf = [0u8; 4096];
+        loop {
+            let mut guard = self.socket.readable().await?;
+            let received = guard.try_io(|socket| {
+                // SAFETY: the buffer is valid for writes of its full length.
+                let n = unsafe {
+                    libc::recv(
+                        socket.as_raw_fd(),
+                        buf.as_mut_ptr() as *mut libc::c_void,
+                        buf.len(),
+                        0,
+                    )
+                };
+                if n < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(n as usize)
+                }
+            });
+            match received {
+                Ok(Ok(n)) => return Ok(parse(&buf[..n])),
+                Ok(Err(e)) if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                    return Ok(vec![ProcEvent::Overrun]);
+                }
+                Ok(Err(e)) => return Err(e),
+                // Not actually readable, wait again.
+                Err(_) => continue,
+            }
+        }
+    }
+}
+
+impl Drop for ProcEvents {
+    fn drop(&mut self) {
+        // The kernel only builds events while someone listens.
+        let _ = control(self.socket.get_ref(), PROC_CN_MCAST_IGNORE);
+    }
+}
+
+/// Sends a `PROC_CN_MCAST_*` operation to the process connector. Its ack
+/// number is argus's PID; the kernel acknowledges with that number plus one.
+fn control(socket: &OwnedFd, operation: u32) -> io::Result<()> {
+    let len = NLMSG_HEADER_LEN + CN_MSG_HEADER_LEN + 4;
+    let mut message = Vec::with_capacity(len);
+    // struct nlmsghdr
+    message.extend_from_slice(&(len as u32).to_ne_bytes());
+    message.extend_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+    message.extend_from_slice(&0u16.to_ne_bytes());
+    message.extend_from_slice(&0u32.to_ne_bytes());
+    message.extend_from_slice(&std::process::id().to_ne_bytes());
+    // struct cn_msg
+    message.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+    message.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+    message.extend_from_slice(&0u32.to_ne_bytes());
+    message.extend_from_slice(&std::process::id().to_ne_bytes());
+    message.extend_from_slice(&4u16.to_ne_bytes());
+    message.extend_from_slice(&0u16.to_ne_bytes());
+    message.extend_from_slice(&operation.to_ne_bytes());
+
+    // SAFETY: the message is valid for reads of its full length.
+    let sent = unsafe {
+        libc::send(
+            socket.as_raw_fd(),
+            message.as_ptr() as *const libc::c_void,
+            message.len(),
+            0,
+        )
+    };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Waits for the kernel to acknowledge [`control`], discarding any events
+/// that arrive first.
+fn wait_for_ack(socket: &OwnedFd) -> io::Result<()> {
+    let deadline = Instant::now() + Duration::from_millis(ACK_TIMEOUT_MS as u64);
+    let mut buf = [0u8; 4096];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let mut poll = libc::pollfd {
+            fd: socket.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: exactly one valid pollfd is passed.
+        let ready = unsafe { libc::poll(&mut poll, 1, remaining.as_millis() as i32) };
+        if ready < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if ready == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the process connector did not acknowledge the subscription",
+            ));
+        }
+        // SAFETY: the buffer is valid for writes of its full length.
+        let n = unsafe {
+            libc::recv(
+                socket.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+            )
+        };
+        if n < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::WouldBlock {
+                continue;
+            }
+            return Err(e);
+        }
+        for message in messages(&buf[..n as usize]) {
+            let ack = read_u32(message, 12);
+            let event = &message[CN_MSG_HEADER_LEN..];
+            if ack == Some(std::process::id().wrapping_add(1))
+                && read_u32(event, 0) == Some(PROC_EVENT_NONE)
+            {
+                return match read_u32(event, EVENT_DATA_OFFSET) {
+                    Some(0) => Ok(()),
+                    Some(err) => Err(io::Error::from_raw_os_error(err as i32)),
+                    None => Err(io::ErrorKind::InvalidData.into()),
+                };
+            }
+        }
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_ne_bytes(bytes.try_into().ok()?))
+}
+
+/// The connector messages (`struct cn_msg` and payload) in a datagram.
+fn messages(mut datagram: &[u8]) -> Vec<&[u8]> {
+    let mut messages = Vec::new();
+    while let Some(len) = read_u32(datagram, 0).map(|len| len as usize) {
+        if len < NLMSG_HEADER_LEN || len > datagram.len() {
+            break;
+        }
+        if let Some(message) = datagram.get(NLMSG_HEADER_LEN..len) {
+            if message.len() >= CN_MSG_HEADER_LEN {
+                messages.push(message);
+            }
+        }
+        // Messages are padded to a multiple of 4 bytes.
+        let next = (len + 3) & !3;
+        datagram = datagram.get(next..).unwrap_or_default();
+    }
+    messages
+}
+
+/// Extracts the process events from a datagram.
+fn parse(datagram: &[u8]) -> Vec<ProcEvent> {
+    messages(datagram)
+        .into_iter()
+        .filter_map(|message| parse_event(&message[CN_MSG_HEADER_LEN..]))
+        .collect()
+}
+
+/// Decodes a `struct proc_event`, ignoring the kinds argus has no use for.
+fn parse_event(event: &[u8]) -> Option<ProcEvent> {
+    let field = |index: usize| read_u32(event, EVENT_DATA_OFFSET + 4 * index);
+    match read_u32(event, 0)? {
+        PROC_EVENT_FORK => {
+            // parent_pid, parent_tgid, child_pid, child_tgid
+            let child = field(2)?;
+            (child == field(3)?).then_some(ProcEvent::Fork { child })
+        }
+        PROC_EVENT_EXEC => Some(ProcEvent::Exec { pid: field(1)? }),
+        PROC_EVENT_EXIT => {
+            // process_pid, process_tgid, exit_code, exit_signal, ...
+            let pid = field(0)?;
+            (pid == field(1)?).then_some(ProcEvent::Exit {
+                pid,
+                status: ExitStatus::from_raw(field(2)? as i32),
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A netlink message carrying a `proc_event` of kind `what` with `data`.
+    fn message(what: u32, data: &[u32]) -> Vec<u8> {
+        let mut event = Vec::new();
+        event.extend_from_slice(&what.to_ne_bytes());
+        event.extend_from_slice(&0u32.to_ne_bytes());
+        event.extend_from_slice(&0u64.to_ne_bytes());
+        for value in data {
+            event.extend_from_slice(&value.to_ne_bytes());
+        }
+
+        let len = NLMSG_HEADER_LEN + CN_MSG_HEADER_LEN + event.len();
+        let mut message = Vec::new();
+        message.extend_from_slice(&(len as u32).to_ne_bytes());
+        message.extend_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+        message.extend_from_slice(&[0; 10]);
+        message.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        message.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        message.extend_from_slice(&[0; 8]);
+        message.extend_from_slice(&(event.len() as u16).to_ne_bytes());
+        message.extend_from_slice(&[0; 2]);
+        message.extend_from_slice(&event);
+        message
+    }
+
+    #[test]
+    fn parses_fork_exec_and_exit() {
+        let mut datagram = message(PROC_EVENT_FORK, &[10, 10, 11, 11]);
+        datagram.extend(message(PROC_EVENT_EXEC, &[11, 11]));
+        datagram.extend(message(PROC_EVENT_EXIT, &[11, 11, 3 << 8, 17, 10, 10]));
+        let events = parse(&datagram);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0], ProcEvent::Fork { child: 11 });
+        assert_eq!(events[1], ProcEvent::Exec { pid: 11 });
+        let ProcEvent::Exit { pid, status } = events[2] else {
+            panic!("expected an exit, got {:?}", events[2]);
+        };
+        assert_eq!(pid, 11);
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[test]
+    fn exit_status_keeps_the_signal() {
+        let events = parse(&message(
+            PROC_EVENT_EXIT,
+            &[5, 5, libc::SIGKILL as u32, 17, 1, 1],
+        ));
+        let [ProcEvent::Exit { status, .. }] = events[..] else {
+            panic!("expected one exit, got {:?}", events);
+        };
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    }
+
+    #[test]
+    fn skips_threads() {
+        // A thread of process 10 being created and exiting.
+        let mut datagram = message(PROC_EVENT_FORK, &[10, 10, 12, 10]);
+        datagram.extend(message(PROC_EVENT_EXIT, &[12, 10, 0, 0, 10, 10]));
+        assert!(parse(&datagram).is_empty());
+    }
+
+    #[test]
+    fn skips_unused_kinds() {
+        // PROC_EVENT_UID and the subscription acknowledgement.
+        let mut datagram = message(0x4, &[10, 10, 1000, 1000]);
+        datagram.extend(message(PROC_EVENT_NONE, &[0]));
+        assert!(parse(&datagram).is_empty());
+        assert_eq!(messages(&datagram).len(), 2);
+    }
+
+    #[test]
+    fn stops_at_truncated_messages() {
+        let mut datagram = message(PROC_EVENT_EXEC, &[7, 7]);
+        let second = message(PROC_EVENT_EXEC, &[8, 8]);
+        datagram.extend(&second[..second.len() - 4]);
+        assert_eq!(parse(&datagram), vec![ProcEvent::Exec { pid: 7 }]);
+        assert!(parse(&[1, 2]).is_empty());
+        assert!(parse(&[]).is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+//! The process table, read directly from `/proc`.
+
+use std::{fs, process, sync::OnceLock, time::Duration};
+
+/// Converts clock ticks, the unit of times in `/proc/<pid>/stat`, to a duration.
+fn from_ticks(ticks: u64) -> Duration {
+    static TICKS_PER_SEC: OnceLock<u64> = OnceLock::new();
+    let per_sec = *TICKS_PER_SEC.get_or_init(|| {
+        // SAFETY: sysconf has no preconditions.
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        u64::try_from(ticks).ok().filter(|&t| t > 0).unwrap_or(100)
+    });
+    Duration::from_secs_f64(ticks as f64 / per_sec as f64)
+}
+
+/// The fields of `/proc/<pid>/stat` argus cares about.
+#[derive(Debug, Clone)]
+pub struct Stat {
+    /// The executable name, cut to 15 bytes by the kernel.
+    pub name: String,
+    /// One of the letters `ps` shows, e.g. R (running), S (sleeping) or Z (zombie).
+    pub state: char,
+    pub parent: u32,
+    /// CPU time spent in user and kernel mode.
+    pub cpu_time: Duration,
+    /// When the process started, relative to boot.
+    pub start_time: Duration,
+}
+
+impl Stat {
+    /// Whether the process still runs, as opposed to having exited without
+    /// being reaped by its parent yet.
+    pub fn is_running(&self) -> bool {
+        !matches!(self.state, 'Z' | 'X' | 'x')
+    }
+}
+
+pub fn stat(pid: u32) -> Option<Stat> {
+    let text = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The name in parentheses may contain spaces and parentheses itself.
+    let name_start = text.find('(')? + 1;
+    let name_end = text.rfind(')')?;
+    let name = text.get(name_start..name_end)?.to_string();
+    let fields: Vec<&str> = text[name_end + 1..].split_whitespace().collect();
+    // Counting from 0 after the name: ppid, utime, stime and starttime are
+    // the 4th, 14th, 15th and 22nd fields of the whole line.
+    let ticks = |index: usize| fields.get(index)?.parse::<u64>().ok();
+    Some(Stat {
+        name,
+        state: fields.first()?.chars().next()?,
+        parent: fields.get(1)?.parse().ok()?,
+        cpu_time: from_ticks(ticks(11)? + ticks(12)?),
+        start_time: from_ticks(ticks(19)?),
+    })
+}
+
+/// The command line a process was started with; empty for kernel threads.
+pub fn cmdline(pid: u32) -> Option<Vec<String>> {
+    let bytes = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    Some(
+        bytes
+            .split(|&byte| byte == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect(),
+    )
+}
+
+/// Running processes other than argus itself for which `predicate` holds.
+pub fn find(predicate: impl Fn(u32, &Stat) -> bool) -> Vec<(u32, Stat)> {
+    let own_pid = process::id();
+    pids()
+        .into_iter()
+        .filter(|&pid| pid != own_pid)
+        .filter_map(|pid| Some((pid, stat(pid)?)))
+        .filter(|(pid, stat)| stat.is_running() && predicate(*pid, stat))
+        .collect()
+}
+
+/// The effective user ID, from `Uid` in `/proc/<pid>/status`.
+pub fn uid(pid: u32) -> Option<u32> {
+    let text = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    text.lines()
+        .find_map(|line| line.strip_prefix("Uid:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+/// Resident memory in bytes, from `VmRSS` in `/proc/<pid>/status`.
+pub fn memory(pid: u32) -> Option<u64> {
+    let text = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let kilobytes = text
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Time since boot, from `/proc/uptime`.
+pub fn uptime() -> Option<Duration> {
+    let text = fs::read_to_string("/proc/uptime").ok()?;
+    let seconds: f64 = text.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// PIDs of all running processes.
+pub fn pids() -> Vec<u32> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect()
+}
+
+/// `pid` followed by its children, grandchildren and so on.
+pub fn with_descendants(pid: u32) -> Vec<u32> {
+    let parents: Vec<(u32, u32)> = pids()
+        .into_iter()
+        .filter_map(|child| Some((child, stat(child)?.parent)))
+        .collect();
+    let mut family = vec![pid];
+    let mut index = 0;
+    while index < family.len() {
+        let parent = family[index];
+        family.extend(
+            parents
+                .iter()
+                .filter(|&&(_, of)| of == parent)
+                .map(|&(child, _)| child),
+        );
+        index += 1;
+    }
+    family
+}
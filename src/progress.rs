@@ -0,0 +1,319 @@
+//! Progress parsed from a command's output, e.g. tqdm bars or "epoch 3/50",
+//! and forwarded to the notifiers at a limited rate.
+
+use regex::{Captures, Regex};
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tokio::{
+    sync::watch,
+    task::{self, JoinHandle},
+    time::{sleep_until, Instant},
+};
+
+use crate::{
+    notifier::{format_duration, NotifierRegistry, Progress, Target},
+    output::{LineSink, Stream},
+};
+
+#[derive(Debug, Clone)]
+pub struct ProgressOptions {
+    /// Pattern with named captures replacing the built-in detection.
+    pub pattern: Option<Regex>,
+    /// Minimum time between two updates sent to the notifiers.
+    pub interval: Duration,
+}
+
+/// Parses a user-supplied progress pattern, which must capture at least
+/// `percent` or `current` and `total`.
+pub fn parse_pattern(text: &str) -> Result<Regex, String> {
+    let pattern = Regex::new(text).map_err(|e| e.to_string())?;
+    let names: Vec<_> = pattern.capture_names().flatten().collect();
+    let has = |name| names.contains(&name);
+    if !(has("percent") || has("current") && has("total")) {
+        return Err(
+            "expected a named group 'percent', or both 'current' and 'total' \
+             (optionally also 'rate' and 'eta')"
+                .to_string(),
+        );
+    }
+    Ok(pattern)
+}
+
+/// Patterns tried in order when no pattern was given.
+fn builtin_patterns() -> &'static [Regex] {
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            // tqdm: " 45%|████▌     | 45/100 [00:04<00:05,  9.87it/s]"
+            r"(?P<percent>\d+(?:\.\d+)?)%\|[^|]*\|\s*(?P<current>\d+(?:\.\d+)?[kMGT]?)/(?P<total>\d+(?:\.\d+)?[kMGT]?)(?:\s*\[[^<\]]*<(?P<eta>[^,\]]+),\s*(?P<rate>[^\]]+)\])?",
+            // "epoch 3/50", "Step 120 of 1000"
+            r"(?i)\b(?:epoch|step|iter(?:ation)?|batch|chunk|file|item|part)s?\s*[:#]?\s*(?P<current>\d+)\s*(?:/|of)\s*(?P<total>\d+)",
+            // "Downloading... 42%"
+            r"(?P<percent>\d{1,3}(?:\.\d+)?)\s?%",
+        ]
+        .iter()
+        .map(|pattern| Regex::new(pattern).unwrap())
+        .collect()
+    })
+}
+
+/// Parses a number, allowing the k/M/G/T suffixes tqdm uses with `unit_scale`.
+fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (digits, multiplier) = match text.char_indices().last()? {
+        (i, 'k') => (&text[..i], 1e3),
+        (i, 'M') => (&text[..i], 1e6),
+        (i, 'G') => (&text[..i], 1e9),
+        (i, 'T') => (&text[..i], 1e12),
+        _ => (text, 1.0),
+    };
+    digits.parse::<f64>().ok().map(|value| value * multiplier)
+}
+
+/// A captured text, unless the command printed a placeholder such as tqdm's "?".
+fn text(captures: &Captures, name: &str) -> Option<String> {
+    let value = captures.name(name)?.as_str().trim();
+    (!value.is_empty() && !value.starts_with('?')).then(|| value.to_string())
+}
+
+fn number(captures: &Captures, name: &str) -> Option<f64> {
+    captures.name(name).and_then(|m| parse_number(m.as_str()))
+}
+
+/// Progress found in `line`, without rate and ETA estimates.
+fn parse(patterns: &[Regex], line: &str) -> Option<Progress> {
+    patterns.iter().find_map(|pattern| {
+        let captures = pattern.captures(line)?;
+        let current = number(&captures, "current");
+        let total = number(&captures, "total").filter(|&total| total > 0.0);
+        let percent = number(&captures, "percent").or_else(|| Some(current? / total? * 100.0))?;
+        if !(0.0..=100.0).contains(&percent) {
+            return None;
+        }
+        Some(Progress {
+            percent: Some(percent),
+            current,
+            total,
+            rate: text(&captures, "rate"),
+            eta: text(&captures, "eta"),
+            elapsed: Duration::ZERO,
+        })
+    })
+}
+
+/// Where progress stood when it was first seen, to estimate rate and ETA from.
+struct Baseline {
+    at: Instant,
+    percent: f64,
+    current: Option<f64>,
+}
+
+/// Parses progress out of captured lines and hands the latest state to the
+/// task started by [`start`].
+pub struct ProgressSink {
+    pattern: Option<Regex>,
+    started: Instant,
+    baseline: Option<Baseline>,
+    updates: watch::Sender<Option<Progress>>,
+}
+
+impl ProgressSink {
+    fn scan(&mut self, line: &str) {
+        let patterns = match &self.pattern {
+            Some(pattern) => std::slice::from_ref(pattern),
+            None => builtin_patterns(),
+        };
+        let Some(mut progress) = parse(patterns, line) else {
+            return;
+        };
+        let now = Instant::now();
+        let percent = progress.percent.unwrap_or_default();
+        progress.elapsed = now - self.started;
+
+        // A counter going backwards means a new bar, e.g. the next epoch's.
+        let restarted = self.baseline.as_ref().is_some_and(|baseline| {
+            percent < baseline.percent
+                || matches!((progress.current, baseline.current), (Some(current), Some(first)) if current < first)
+        });
+        if self.baseline.is_none() || restarted {
+            self.baseline = Some(Baseline {
+                at: now,
+                percent,
+                current: progress.current,
+            });
+        }
+        let baseline = self.baseline.as_ref().unwrap();
+        let seconds = (now - baseline.at).as_secs_f64();
+        if seconds > 0.0 {
+            if progress.rate.is_none() {
+                if let (Some(current), Some(start)) = (progress.current, baseline.current) {
+                    progress.rate = Some(format!("{:.2}/s", (current - start) / seconds));
+                }
+            }
+            let speed = (percent - baseline.percent) / seconds;
+            if progress.eta.is_none() && speed > 0.0 {
+                // Too far out to be represented means too far out to be useful.
+                progress.eta = Duration::try_from_secs_f64((100.0 - percent) / speed)
+                    .ok()
+                    .map(format_duration);
+            }
+        }
+        self.updates.send_replace(Some(progress));
+    }
+}
+
+impl LineSink for ProgressSink {
+    fn line(&mut self, _stream: Stream, line: &str) {
+        self.scan(line);
+    }
+
+    fn overwritten(&mut self, _stream: Stream, text: &str) {
+        self.scan(text);
+    }
+}
+
+/// Starts looking for progress in the output. The returned task ends once
+/// the sink is dropped and the last update has been delivered.
+pub fn start(
+    options: ProgressOptions,
+    notifiers: Arc<NotifierRegistry>,
+    target: Target,
+) -> (ProgressSink, JoinHandle<()>) {
+    let (tx, rx) = watch::channel(None);
+    let sink = ProgressSink {
+        pattern: options.pattern,
+        started: Instant::now(),
+        baseline: None,
+        updates: tx,
+    };
+    let task = task::spawn(report(rx, options.interval, notifiers, target));
+    (sink, task)
+}
+
+/// Sends the latest progress at most once per `interval`.
+async fn report(
+    mut updates: watch::Receiver<Option<Progress>>,
+    interval: Duration,
+    notifiers: Arc<NotifierRegistry>,
+    target: Target,
+) {
+    let mut next_send = Instant::now();
+    let mut unsent = false;
+    loop {
+        tokio::select! {
+            changed = updates.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                unsent = true;
+            }
+            _ = sleep_until(next_send), if unsent => {}
+        }
+        if unsent && Instant::now() >= next_send {
+            let progress = updates.borrow_and_update().clone();
+            if let Some(progress) = progress {
+                notifiers.progress(&target, &progress).await;
+            }
+            unsent = false;
+            next_send = Instant::now() + interval;
+        }
+    }
+    if unsent {
+        let progress = updates.borrow().clone();
+        if let Some(progress) = progress {
+            notifiers.progress(&target, &progress).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin(line: &str) -> Option<Progress> {
+        parse(builtin_patterns(), line)
+    }
+
+    #[test]
+    fn parses_tqdm_bars() {
+        let progress = builtin(" 45%|████▌     | 45/100 [00:04<00:05,  9.87it/s]").unwrap();
+        assert_eq!(progress.percent, Some(45.0));
+        assert_eq!(progress.current, Some(45.0));
+        assert_eq!(progress.total, Some(100.0));
+        assert_eq!(progress.eta.as_deref(), Some("00:05"));
+        assert_eq!(progress.rate.as_deref(), Some("9.87it/s"));
+    }
+
+    #[test]
+    fn parses_tqdm_unit_scale_and_placeholders() {
+        let progress = builtin(" 12%|█▏        | 1.2k/10.0k [00:01<?, ?it/s]").unwrap();
+        assert_eq!(progress.current, Some(1200.0));
+        assert_eq!(progress.total, Some(10_000.0));
+        assert_eq!(progress.eta, None);
+        assert_eq!(progress.rate, None);
+    }
+
+    #[test]
+    fn parses_counters() {
+        let progress = builtin("Epoch 3/50, loss 0.12").unwrap();
+        assert_eq!(progress.current, Some(3.0));
+        assert_eq!(progress.total, Some(50.0));
+        assert_eq!(progress.percent, Some(6.0));
+
+        let progress = builtin("step 120 of 1000").unwrap();
+        assert_eq!(progress.percent, Some(12.0));
+    }
+
+    #[test]
+    fn parses_percentages() {
+        assert_eq!(builtin("Downloading... 42%").unwrap().percent, Some(42.0));
+        assert_eq!(builtin("done: 99.5 %").unwrap().percent, Some(99.5));
+        assert_eq!(builtin("all quiet"), None);
+        assert_eq!(builtin("grew by 250%"), None);
+    }
+
+    #[test]
+    fn ignores_counters_with_zero_total() {
+        assert_eq!(builtin("epoch 0/0"), None);
+    }
+
+    #[test]
+    fn custom_patterns_need_the_right_groups() {
+        assert!(parse_pattern(r"(?P<percent>\d+)").is_ok());
+        assert!(parse_pattern(r"(?P<current>\d+)/(?P<total>\d+)").is_ok());
+        assert!(parse_pattern(r"(?P<current>\d+)").is_err());
+        assert!(parse_pattern(r"(").is_err());
+
+        let pattern = parse_pattern(r"done (?P<current>\d+) of (?P<total>\d+)").unwrap();
+        let progress = parse(std::slice::from_ref(&pattern), "done 1 of 4").unwrap();
+        assert_eq!(progress.percent, Some(25.0));
+    }
+
+    #[test]
+    fn parses_suffixed_numbers() {
+        assert_eq!(parse_number("12"), Some(12.0));
+        assert_eq!(parse_number("1.5M"), Some(1_500_000.0));
+        assert_eq!(parse_number(" 2k "), Some(2000.0));
+        assert_eq!(parse_number("k"), None);
+        assert_eq!(parse_number(""), None);
+    }
+
+    #[test]
+    fn leaves_out_unrepresentable_eta() {
+        let (updates, receiver) = watch::channel(None);
+        let mut sink = ProgressSink {
+            pattern: None,
+            started: Instant::now(),
+            baseline: None,
+            updates,
+        };
+        sink.scan("step 1 of 99999999999999999999999999");
+        std::thread::sleep(Duration::from_millis(10));
+        sink.scan("step 2 of 99999999999999999999999999");
+        let progress = receiver.borrow().clone().unwrap();
+        assert_eq!(progress.current, Some(2.0));
+        assert_eq!(progress.eta, None);
+    }
+}
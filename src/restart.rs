@@ -0,0 +1,236 @@
+//! Restarting `exec` commands when they exit, with backoff and crash-loop
+//! detection.
+
+use clap::ValueEnum;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::notifier::GiveUpReason;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RestartPolicy {
+    /// Restart only after a non-zero exit, a signal or a timeout
+    OnFailure,
+    /// Restart whenever the command exits
+    Always,
+}
+
+#[derive(Debug, Clone)]
+pub struct RestartOptions {
+    pub policy: RestartPolicy,
+    /// Restarts allowed in total, unlimited if `None`.
+    pub max_restarts: Option<usize>,
+    /// Delay before the first restart, doubled for each consecutive failure.
+    pub delay: Duration,
+    pub max_delay: Duration,
+    /// Give up once this many failures happen within `crash_loop_window`; 0 disables.
+    pub crash_loop_failures: usize,
+    pub crash_loop_window: Duration,
+}
+
+/// What to do after the command exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Leave it stopped, as the policy asks.
+    Stop,
+    /// Start it again after `delay`. `restart` counts from 1.
+    Restart {
+        restart: usize,
+        delay: Duration,
+    },
+    GiveUp(GiveUpReason),
+}
+
+/// Decides whether and when a command is restarted.
+pub struct Restarter {
+    options: RestartOptions,
+    restarts: usize,
+    /// Delay before the next restart.
+    delay: Duration,
+    /// When recent failures happened, oldest first.
+    failures: VecDeque<Instant>,
+}
+
+impl Restarter {
+    pub fn new(options: RestartOptions) -> Self {
+        Self {
+            delay: options.delay,
+            options,
+            restarts: 0,
+            failures: VecDeque::new(),
+        }
+    }
+
+    pub fn restarts(&self) -> usize {
+        self.restarts
+    }
+
+    pub fn max_restarts(&self) -> Option<usize> {
+        self.options.max_restarts
+    }
+
+    /// Decides what follows a run that lasted `ran_for`.
+    pub fn next(&mut self, failed: bool, ran_for: Duration) -> Decision {
+        if !failed && self.options.policy == RestartPolicy::OnFailure {
+            return Decision::Stop;
+        }
+        if self
+            .options
+            .max_restarts
+            .is_some_and(|max| self.restarts >= max)
+        {
+            return Decision::GiveUp(GiveUpReason::RetriesExhausted);
+        }
+
+        let window = self.options.crash_loop_window;
+        if failed {
+            let now = Instant::now();
+            self.failures.push_back(now);
+            while self
+                .failures
+                .front()
+                .is_some_and(|&failure| now - failure > window)
+            {
+                self.failures.pop_front();
+            }
+            let threshold = self.options.crash_loop_failures;
+            if threshold > 0 && self.failures.len() >= threshold {
+                return Decision::GiveUp(GiveUpReason::CrashLoop {
+                    failures: self.failures.len(),
+                    window,
+                });
+            }
+        }
+
+        // Back off only while the command keeps failing quickly.
+        if !failed || ran_for >= window {
+            self.delay = self.options.delay;
+        }
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(self.options.max_delay);
+        self.restarts += 1;
+        Decision::Restart {
+            restart: self.restarts,
+            delay,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn options(policy: RestartPolicy) -> RestartOptions {
+        RestartOptions {
+            policy,
+            max_restarts: None,
+            delay: SECOND,
+            max_delay: 5 * SECOND,
+            crash_loop_failures: 0,
+            crash_loop_window: 60 * SECOND,
+        }
+    }
+
+    fn delay(decision: Decision) -> Duration {
+        match decision {
+            Decision::Restart { delay, .. } => delay,
+            other => panic!("expected a restart, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn on_failure_stops_after_success() {
+        let mut restarter = Restarter::new(options(RestartPolicy::OnFailure));
+        assert_eq!(restarter.next(false, SECOND), Decision::Stop);
+        assert_eq!(
+            restarter.next(true, SECOND),
+            Decision::Restart {
+                restart: 1,
+                delay: SECOND
+            }
+        );
+    }
+
+    #[test]
+    fn always_restarts_after_success() {
+        let mut restarter = Restarter::new(options(RestartPolicy::Always));
+        assert_eq!(
+            restarter.next(false, SECOND),
+            Decision::Restart {
+                restart: 1,
+                delay: SECOND
+            }
+        );
+    }
+
+    #[test]
+    fn backs_off_up_to_the_maximum() {
+        let mut restarter = Restarter::new(options(RestartPolicy::OnFailure));
+        let delays: Vec<u64> = (0..5)
+            .map(|_| delay(restarter.next(true, SECOND)).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 5, 5]);
+        assert_eq!(restarter.restarts(), 5);
+    }
+
+    #[test]
+    fn backoff_resets_after_a_long_or_successful_run() {
+        let mut restarter = Restarter::new(options(RestartPolicy::Always));
+        delay(restarter.next(true, SECOND));
+        assert_eq!(delay(restarter.next(true, SECOND)), 2 * SECOND);
+        // Ran for the whole crash-loop window.
+        assert_eq!(delay(restarter.next(true, 60 * SECOND)), SECOND);
+        assert_eq!(delay(restarter.next(true, SECOND)), 2 * SECOND);
+        assert_eq!(delay(restarter.next(false, SECOND)), SECOND);
+    }
+
+    #[test]
+    fn gives_up_after_max_restarts() {
+        let mut restarter = Restarter::new(RestartOptions {
+            max_restarts: Some(2),
+            ..options(RestartPolicy::OnFailure)
+        });
+        delay(restarter.next(true, SECOND));
+        delay(restarter.next(true, SECOND));
+        assert_eq!(
+            restarter.next(true, SECOND),
+            Decision::GiveUp(GiveUpReason::RetriesExhausted)
+        );
+        assert_eq!(restarter.max_restarts(), Some(2));
+    }
+
+    #[test]
+    fn detects_crash_loops() {
+        let mut restarter = Restarter::new(RestartOptions {
+            crash_loop_failures: 3,
+            ..options(RestartPolicy::OnFailure)
+        });
+        delay(restarter.next(true, SECOND));
+        // Successful runs do not count towards the crash loop.
+        assert_eq!(restarter.next(false, SECOND), Decision::Stop);
+        delay(restarter.next(true, SECOND));
+        assert_eq!(
+            restarter.next(true, SECOND),
+            Decision::GiveUp(GiveUpReason::CrashLoop {
+                failures: 3,
+                window: 60 * SECOND
+            })
+        );
+    }
+
+    #[test]
+    fn crash_loop_forgets_old_failures() {
+        let mut restarter = Restarter::new(RestartOptions {
+            crash_loop_failures: 2,
+            crash_loop_window: Duration::from_millis(20),
+            ..options(RestartPolicy::OnFailure)
+        });
+        delay(restarter.next(true, SECOND));
+        std::thread::sleep(Duration::from_millis(40));
+        delay(restarter.next(true, SECOND));
+    }
+}
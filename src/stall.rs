@@ -0,0 +1,144 @@
+//! Notices for targets that stop making progress without exiting, and for
+//! when they pick up again.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    notifier::{Activity, Event, NotifierRegistry, Target},
+    procfs,
+};
+
+/// How often stall detection looks at a spawned command's output.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// CPU time of each process by PID, with the start time that tells a reused
+/// PID apart.
+type CpuTimes = HashMap<u32, (Duration, Duration)>;
+
+/// Whether a process present in both samples used CPU time in between.
+/// Processes exiting or appearing change the total but are no activity.
+fn used_cpu(previous: &CpuTimes, current: &CpuTimes) -> bool {
+    current.iter().any(|(pid, &(start_time, cpu_time))| {
+        previous
+            .get(pid)
+            .is_some_and(|&(previous_start, previous_cpu)| {
+                previous_start == start_time && cpu_time > previous_cpu
+            })
+    })
+}
+
+/// Tracks the last sign of activity of a target and reports when it has been
+/// quiet for longer than the timeout.
+pub struct StallDetector {
+    timeout: Duration,
+    activity: Activity,
+    notifiers: Arc<NotifierRegistry>,
+    target: Target,
+    pid: Option<u32>,
+    last_activity: Instant,
+    /// Whether a stall was reported and activity has not resumed since.
+    stalled: bool,
+    /// CPU time of the watched processes at the previous CPU check.
+    cpu_times: Option<CpuTimes>,
+}
+
+impl StallDetector {
+    pub fn new(
+        timeout: Duration,
+        activity: Activity,
+        notifiers: Arc<NotifierRegistry>,
+        target: Target,
+        pid: Option<u32>,
+    ) -> Self {
+        Self {
+            timeout,
+            activity,
+            notifiers,
+            target,
+            pid,
+            last_activity: Instant::now(),
+            stalled: false,
+            cpu_times: None,
+        }
+    }
+
+    /// Checks output activity, given when the command last printed anything.
+    pub async fn check_output(&mut self, last_output: Instant) {
+        self.check(last_output).await;
+    }
+
+    /// Checks whether `pids` used any CPU time since the previous check.
+    pub async fn check_cpu(&mut self, pids: &[u32]) {
+        let cpu_times: CpuTimes = pids
+            .iter()
+            .filter_map(|&pid| {
+                procfs::stat(pid).map(|stat| (pid, (stat.start_time, stat.cpu_time)))
+            })
+            .collect();
+        let active = self
+            .cpu_times
+            .as_ref()
+            .is_some_and(|previous| used_cpu(previous, &cpu_times));
+        self.cpu_times = Some(cpu_times);
+        let last_activity = if active {
+            Instant::now()
+        } else {
+            self.last_activity
+        };
+        self.check(last_activity).await;
+    }
+
+    async fn check(&mut self, last_activity: Instant) {
+        if last_activity > self.last_activity {
+            if self.stalled {
+                self.stalled = false;
+                let idle = last_activity - self.last_activity;
+                let event = Event::resumed(self.target.clone(), self.pid, self.activity, idle);
+                self.notifiers.notify(&event).await;
+            }
+            self.last_activity = last_activity;
+        } else if !self.stalled && self.last_activity.elapsed() >= self.timeout {
+            self.stalled = true;
+            let idle = self.last_activity.elapsed();
+            let event = Event::stalled(self.target.clone(), self.pid, self.activity, idle);
+            self.notifiers.notify(&event).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(processes: &[(u32, u64, u64)]) -> CpuTimes {
+        processes
+            .iter()
+            .map(|&(pid, start, cpu)| {
+                (
+                    pid,
+                    (Duration::from_secs(start), Duration::from_millis(cpu)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cpu_use_of_a_running_process_is_activity() {
+        let previous = sample(&[(1, 5, 100), (2, 6, 200)]);
+        assert!(used_cpu(&previous, &sample(&[(1, 5, 100), (2, 6, 250)])));
+        assert!(!used_cpu(&previous, &sample(&[(1, 5, 100), (2, 6, 200)])));
+    }
+
+    #[test]
+    fn processes_coming_and_going_are_no_activity() {
+        let previous = sample(&[(1, 5, 100), (2, 6, 200)]);
+        // 2 exited and 3 started.
+        assert!(!used_cpu(&previous, &sample(&[(1, 5, 100), (3, 7, 900)])));
+        // 2 exited and its PID was reused.
+        assert!(!used_cpu(&previous, &sample(&[(1, 5, 100), (2, 8, 300)])));
+    }
+}